@@ -0,0 +1,388 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! The `merge`, `split` and `slice` commands.
+//!
+//! Records are copied unchanged, so frames keep their timestamps and
+//! properties. Attachments, e.g., network keys, are needed to make sense of
+//! any part of a trace, so `split` and `slice` copy all of them to the start
+//! of every output file.
+use std::error::Error;
+use std::fs::File;
+use std::io::{
+    BufReader,
+    BufWriter,
+};
+use std::path::Path;
+use zniff_rs_core::zlf::{
+    ZlfHeader,
+    ZlfIndex,
+    ZlfReader,
+    ZlfRecord,
+    ZlfWriter,
+    types::{
+        ApiType,
+        ZlfTimestamp,
+        RECORD_OVERHEAD,
+        ZLF_HEADER_SIZE,
+    },
+};
+
+type Reader = ZlfReader<BufReader<File>>;
+type Writer = ZlfWriter<BufWriter<File>>;
+
+fn open(path: &str) -> Result<Reader, Box<dyn Error>> {
+    Ok(ZlfReader::new(BufReader::new(File::open(path)?))?)
+}
+
+fn create(path: &str, header: &ZlfHeader) -> Result<Writer, Box<dyn Error>> {
+    Ok(ZlfWriter::with_header(BufWriter::new(File::create(path)?), header)?)
+}
+
+fn record_size(record: &ZlfRecord) -> u64 {
+    let payload_length = match record {
+        ZlfRecord::Attachment(attachment) => attachment.data.len(),
+        ZlfRecord::Other(raw) => raw.payload.len(),
+        ZlfRecord::Data(df) => df.frame.to_zniffer_vector().len(),
+    };
+    RECORD_OVERHEAD + payload_length as u64
+}
+
+/// All attachment records of the trace, located through the index.
+fn read_attachments(reader: &mut Reader, index: &ZlfIndex) -> Result<Vec<ZlfRecord>, Box<dyn Error>> {
+    let mut attachments = Vec::new();
+    for entry in index.records().iter().filter(|entry| entry.api_type == ApiType::Attachment) {
+        reader.seek_record(entry)?;
+        if let Some(record) = reader.next()? {
+            attachments.push(record);
+        }
+    }
+    Ok(attachments)
+}
+
+/// Interleave the records of several traces by timestamp. Records with the
+/// same timestamp are taken in the order the inputs are given.
+pub fn merge(inputs: &[String], output: &str, comment: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut readers = inputs.iter().map(|input| open(input)).collect::<Result<Vec<_>, _>>()?;
+
+    // Keep the header of the first input, with the comments of all inputs as
+    // far as they fit.
+    let mut header = readers[0].header().clone();
+    match comment {
+        Some(comment) => header.comment = comment.to_string(),
+        None => {
+            let mut comments: Vec<&str> = Vec::new();
+            for reader in &readers {
+                let comment = reader.header().comment.as_str();
+                if !comment.is_empty() && !comments.contains(&comment) {
+                    comments.push(comment);
+                }
+            }
+            header.comment = comments.join("; ");
+            header.truncate_comment();
+        },
+    }
+    let mut writer = create(output, &header)?;
+
+    let mut heads = readers.iter_mut().map(|reader| reader.next()).collect::<Result<Vec<_>, _>>()?;
+    let mut record_counter = 0;
+    loop {
+        let next = heads
+            .iter()
+            .enumerate()
+            .filter_map(|(i, head)| head.as_ref().map(|record| (i, record.timestamp().ticks())))
+            .min_by_key(|(_, ticks)| *ticks);
+        let Some((i, _)) = next else {
+            break;
+        };
+
+        writer.write_record(heads[i].as_ref().unwrap())?;
+        record_counter += 1;
+        heads[i] = readers[i].next()?;
+    }
+    writer.finish()?;
+
+    println!("Merge complete. Wrote {} record(s) to {}", record_counter, output);
+    Ok(())
+}
+
+/// Limit of each part written by `split`.
+pub enum SplitLimit {
+    /// File size in bytes.
+    Size(u64),
+    /// Number of frame records.
+    Frames(usize),
+    /// Time span in seconds.
+    Duration(u64),
+}
+
+/// Parse a size in bytes with an optional K, M or G suffix, e.g., `100M`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let (number, multiplier) = match s.to_ascii_uppercase().chars().last() {
+        Some('K') => (&s[..s.len() - 1], 1 << 10),
+        Some('M') => (&s[..s.len() - 1], 1 << 20),
+        Some('G') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    let n = number.parse::<u64>().map_err(|_| format!("invalid size: {}", s))?;
+    n.checked_mul(multiplier).ok_or_else(|| format!("invalid size: {}", s))
+}
+
+/// Path of part `n` of a split, e.g., `capture_003.zlf` for `capture.zlf`.
+fn part_path(output: &str, n: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("part");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("zlf");
+    path.with_file_name(format!("{}_{:03}.{}", stem, n, extension))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Split a trace into parts limited by size, frame count or time span.
+pub fn split(input: &str, output: &str, limit: SplitLimit) -> Result<(), Box<dyn Error>> {
+    let index = ZlfIndex::open(input)?;
+    let mut reader = open(input)?;
+    let attachments = read_attachments(&mut reader, &index)?;
+    let header = reader.header().clone();
+    let attachments_size: u64 = attachments.iter().map(record_size).sum();
+
+    let mut part: Option<Writer> = None;
+    let mut part_counter = 0;
+    let mut part_start = ZlfTimestamp::default();
+    let mut part_size = 0u64;
+
+    if let Some(first) = index.frame(0) {
+        reader.seek_record(first)?;
+    }
+    while let Some(record) = reader.next()? {
+        if matches!(record, ZlfRecord::Attachment(_)) {
+            continue;
+        }
+        let size = record_size(&record);
+
+        let full = match (&part, &limit) {
+            (None, _) => true,
+            (Some(writer), SplitLimit::Frames(frames)) => writer.frame_count() >= *frames,
+            (Some(_), SplitLimit::Duration(seconds)) => {
+                record.timestamp().unix_micros() - part_start.unix_micros() >= *seconds as i64 * 1_000_000
+            },
+            (Some(writer), SplitLimit::Size(bytes)) => writer.frame_count() > 0 && part_size + size > *bytes,
+        };
+        if full {
+            if let Some(writer) = part.take() {
+                writer.finish()?;
+            }
+            part_counter += 1;
+            let path = part_path(output, part_counter);
+            println!("Writing {}", path);
+            let mut writer = create(&path, &header)?;
+            for attachment in &attachments {
+                writer.write_record(attachment)?;
+            }
+            part = Some(writer);
+            part_start = record.timestamp();
+            part_size = ZLF_HEADER_SIZE as u64 + attachments_size;
+        }
+
+        part.as_mut().unwrap().write_record(&record)?;
+        part_size += size;
+    }
+    if let Some(writer) = part {
+        writer.finish()?;
+    }
+
+    println!("Split complete. Wrote {} frame record(s) to {} file(s)", index.frame_count(), part_counter);
+    Ok(())
+}
+
+/// Range of frames to keep in `slice`.
+pub enum SliceRange {
+    /// Frame numbers, counting from 1, both inclusive.
+    Frames(Option<usize>, Option<usize>),
+    /// Start time (inclusive) and end time (exclusive).
+    Time(Option<ZlfTimestamp>, Option<ZlfTimestamp>),
+}
+
+/// Copy a range of frames, located through the index, to a new trace.
+pub fn slice(input: &str, output: &str, range: SliceRange) -> Result<(), Box<dyn Error>> {
+    let index = ZlfIndex::open(input)?;
+    let (start, end) = match range {
+        SliceRange::Frames(first, last) => (
+            first.unwrap_or(1).saturating_sub(1),
+            last.unwrap_or(usize::MAX),
+        ),
+        SliceRange::Time(from, to) => (
+            from.map_or(0, |from| index.seek_time(from)),
+            to.map_or(usize::MAX, |to| index.seek_time(to)),
+        ),
+    };
+    let end = end.min(index.frame_count());
+
+    let mut reader = open(input)?;
+    let attachments = read_attachments(&mut reader, &index)?;
+    let mut writer = create(output, reader.header())?;
+    for attachment in &attachments {
+        writer.write_record(attachment)?;
+    }
+
+    if let Some(first) = index.frame(start).filter(|_| start < end) {
+        reader.seek_record(first)?;
+        while writer.frame_count() < end - start {
+            match reader.next()? {
+                Some(ZlfRecord::Attachment(_)) => {},
+                Some(record) => writer.write_record(&record)?,
+                None => break,
+            }
+        }
+    }
+    let frame_counter = writer.frame_count();
+    writer.finish()?;
+
+    println!("Slice complete. Wrote {} frame record(s) to {}", frame_counter, output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn at(seconds: i64) -> ZlfTimestamp {
+        ZlfTimestamp::from_unix_micros(1_700_000_000_000_000 + seconds * 1_000_000)
+    }
+
+    /// A fresh directory of the temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("zniff-rs-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Write a trace with an attachment followed by 6-byte frames tagged
+    /// with `tag`, one at each of `seconds`.
+    fn write_trace(path: &Path, comment: &str, tag: u8, seconds: &[i64]) -> String {
+        let file = BufWriter::new(File::create(path).unwrap());
+        let mut writer = ZlfWriter::with_header(file, &ZlfHeader::with_comment(comment)).unwrap();
+        writer.write_attachment(at(-1), b"keys").unwrap();
+        for s in seconds {
+            writer.write_zniffer(at(*s), &[tag; 6]).unwrap();
+        }
+        writer.finish().unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Attachment count and the (second, tag) of each frame of a trace.
+    fn read_trace(path: &str) -> (usize, Vec<(i64, u8)>) {
+        let mut reader = open(path).unwrap();
+        let mut attachments = 0;
+        let mut frames = Vec::new();
+        while let Some(record) = reader.next().unwrap() {
+            match record {
+                ZlfRecord::Attachment(_) => attachments += 1,
+                ZlfRecord::Other(raw) => frames.push(((raw.timestamp.unix_micros() - at(0).unix_micros()) / 1_000_000, raw.payload[0])),
+                ZlfRecord::Data(_) => panic!("unexpected data frame"),
+            }
+        }
+        (attachments, frames)
+    }
+
+    /// The first `n` parts written by `split`.
+    fn parts(output: &str, n: usize) -> Vec<(usize, Vec<(i64, u8)>)> {
+        (1..=n).map(|i| read_trace(&part_path(output, i))).collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let dir = temp_dir("merge");
+        let a = write_trace(&dir.join("a.zlf"), "first", 0xA0, &[1, 3, 5]);
+        let b = write_trace(&dir.join("b.zlf"), "second", 0xB0, &[2, 3, 4]);
+        let output = dir.join("merged.zlf").to_string_lossy().into_owned();
+
+        merge(&[a.clone(), b.clone()], &output, None).unwrap();
+        assert_eq!(open(&output).unwrap().header().comment, "first; second");
+        let (attachments, frames) = read_trace(&output);
+        assert_eq!(attachments, 2);
+        assert_eq!(frames, [(1, 0xA0), (2, 0xB0), (3, 0xA0), (3, 0xB0), (4, 0xB0), (5, 0xA0)]);
+
+        merge(&[b, a], &output, Some("merged")).unwrap();
+        assert_eq!(open(&output).unwrap().header().comment, "merged");
+        assert_eq!(read_trace(&output).1[2..4], [(3, 0xB0), (3, 0xA0)]);
+
+        // The combined comments are cut to fit the header.
+        let a = write_trace(&dir.join("a.zlf"), &"a".repeat(300), 0xA0, &[1]);
+        let b = write_trace(&dir.join("b.zlf"), &"ö".repeat(150), 0xB0, &[2]);
+        merge(&[a, b], &output, None).unwrap();
+        let comment = open(&output).unwrap().header().comment.clone();
+        assert_eq!(comment.len(), 512);
+        assert_eq!(comment, format!("{}; {}", "a".repeat(300), "ö".repeat(105)));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4K"), Ok(4 << 10));
+        assert_eq!(parse_size("100m"), Ok(100 << 20));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1.5M").is_err());
+        assert_eq!(parse_size("20000000000G"), Err("invalid size: 20000000000G".to_string()));
+    }
+
+    #[test]
+    fn test_split() {
+        let dir = temp_dir("split");
+        let input = write_trace(&dir.join("trace.zlf"), "", 0x21, &(0..10).collect::<Vec<_>>());
+        let output = dir.join("part.zlf").to_string_lossy().into_owned();
+        let seconds = |part: &(usize, Vec<(i64, u8)>)| part.1.iter().map(|(s, _)| *s).collect::<Vec<_>>();
+
+        split(&input, &output, SplitLimit::Frames(4)).unwrap();
+        let frames = parts(&output, 3);
+        assert!(frames.iter().all(|part| part.0 == 1));
+        assert_eq!(frames.iter().map(seconds).collect::<Vec<_>>(), [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+        assert!(!Path::new(&part_path(&output, 4)).exists());
+
+        let dir = temp_dir("split-duration");
+        let output = dir.join("part.zlf").to_string_lossy().into_owned();
+        split(&input, &output, SplitLimit::Duration(3)).unwrap();
+        assert_eq!(parts(&output, 4).iter().map(seconds).collect::<Vec<_>>(), [vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9]]);
+        assert!(!Path::new(&part_path(&output, 5)).exists());
+
+        // Header, attachment and three frames per part.
+        let dir = temp_dir("split-size");
+        let output = dir.join("part.zlf").to_string_lossy().into_owned();
+        let limit = ZLF_HEADER_SIZE as u64 + (RECORD_OVERHEAD + 4) + 3 * (RECORD_OVERHEAD + 6);
+        split(&input, &output, SplitLimit::Size(limit)).unwrap();
+        let frames = parts(&output, 4);
+        assert_eq!(frames.iter().map(|part| part.1.len()).collect::<Vec<_>>(), [3, 3, 3, 1]);
+        for i in 1..=4 {
+            assert!(std::fs::metadata(part_path(&output, i)).unwrap().len() <= limit);
+        }
+    }
+
+    #[test]
+    fn test_slice() {
+        let dir = temp_dir("slice");
+        let input = write_trace(&dir.join("trace.zlf"), "", 0x21, &(1..=10).collect::<Vec<_>>());
+        let output = dir.join("slice.zlf").to_string_lossy().into_owned();
+        let sliced = |range: SliceRange| {
+            slice(&input, &output, range).unwrap();
+            let (attachments, frames) = read_trace(&output);
+            assert_eq!(attachments, 1);
+            frames.iter().map(|(s, _)| *s).collect::<Vec<_>>()
+        };
+
+        assert_eq!(sliced(SliceRange::Frames(Some(3), Some(5))), [3, 4, 5]);
+        assert_eq!(sliced(SliceRange::Frames(None, Some(2))), [1, 2]);
+        assert_eq!(sliced(SliceRange::Frames(Some(9), None)), [9, 10]);
+        assert_eq!(sliced(SliceRange::Frames(None, Some(100))).len(), 10);
+        assert_eq!(sliced(SliceRange::Frames(Some(20), None)), Vec::<i64>::new());
+        assert_eq!(sliced(SliceRange::Frames(Some(5), Some(4))), Vec::<i64>::new());
+
+        assert_eq!(sliced(SliceRange::Time(Some(at(3)), Some(at(6)))), [3, 4, 5]);
+        assert_eq!(sliced(SliceRange::Time(None, Some(at(3)))), [1, 2]);
+        assert_eq!(sliced(SliceRange::Time(Some(at(9)), None)), [9, 10]);
+        assert_eq!(sliced(SliceRange::Time(Some(at(20)), None)), Vec::<i64>::new());
+        assert_eq!(sliced(SliceRange::Time(None, Some(at(1)))), Vec::<i64>::new());
+    }
+}
@@ -24,7 +24,7 @@ impl FrameGenerator {
         //println!("Generate something! {:?}", from_file);
         let file = File::open(self.file)?;
         //let file_length = file.metadata()?.len();
-        let mut reader = ZlfReader::new(file)?;
+        let reader = ZlfReader::new(file)?;
 
         let listener = TcpListener::bind("0.0.0.0:9000")?;
         println!("Waiting for client...");
@@ -32,23 +32,23 @@ impl FrameGenerator {
         println!("Client connected from {addr}");
 
         let mut n = 0usize;
-        while let Some(rec) = reader.next()? {
-            n += 1;
-            match rec {
+        for rec in reader.into_frames() {
+            match rec? {
                 ZlfRecord::Data(df) => {
+                    n += 1;
                     println!(
-                        "#{:06} DATA ts={} ch/s={} region={} rssi={} mpdu_len={}",
-                        n, df.timestamp, df.ch_and_speed, df.region, df.rssi, df.mpdu.len()
+                        "#{:06} {:?} time={} ch={} speed={} region={:?} rssi={} mpdu_len={}",
+                        n, df.frame.frame_type, df.timestamp, df.frame.channel, df.frame.speed,
+                        df.frame.region, df.frame.rssi as i8, df.frame.payload.len()
                     );
-                    // … further MPDU parsing here …
+                    stream.write_all(&df.frame.to_zniffer_vector())?;
+                    std::thread::sleep(std::time::Duration::from_millis(self.delay as u64));
                 }
                 ZlfRecord::Other(raw) => {
                     println!(
-                        "#{:06} {:?} sof='{}' len={}",
-                        n, raw.frame_type, raw.sof as char, raw.payload.len()
+                        "{:?} sof='{}' len={}",
+                        raw.frame_type, raw.sof as char, raw.payload.len()
                     );
-                    stream.write_all(&raw.payload)?;
-                    std::thread::sleep(std::time::Duration::from_millis(self.delay as u64));
                 }
                 _ => {
                     // Skip other records (e.g., attachments) for now.
@@ -56,7 +56,6 @@ impl FrameGenerator {
             }
         }
         println!("End of file reached after {} frames", n);
-        println!("Total frames read: {}", reader.frame_count());
         Ok(())
     }
 }
@@ -1,4 +1,5 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
+#[allow(clippy::module_inception)]
 pub mod generator;
 pub use generator::FrameGenerator;
@@ -1,30 +1,51 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
-mod frame_definition;
-mod xml;
-use std::time::Duration;
-use std::io::{self, Write, Read};
+use std::io;
+use std::path::PathBuf;
 
 use clap::{
     Parser,
     Subcommand,
     value_parser,
 };
-use serialport::SerialPort;
+use serde::Serialize;
 
+use zniff_rs_core::beam::{
+    Beam,
+    BeamTracker,
+};
+use zniff_rs_core::decoder::{
+    DecodedFrame,
+    Decoder,
+    Definitions,
+};
 use zniff_rs_core::types::{
     Frame,
+    FrameType,
     Region,
 };
 mod zw_parser;
 use zw_parser::ZwParser;
+use zniff_rs_core::mac::Layout;
 use zniff_rs_core::zlf;
 
-use zniff_rs_core::zniffer_parser;
+use zniff_rs_core::device::{
+    self,
+    Emulator,
+    SerialStream,
+    Zniffer,
+};
+use zniff_rs_core::zniffer_command::{
+    BaudRate,
+    Version,
+    ZnifferError,
+};
 
 mod generator;
 use crate::generator::FrameGenerator;
 
+mod edit;
+
 use tokio::{
     io::{
         AsyncWriteExt,
@@ -43,6 +64,16 @@ use tokio::{
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Command class definitions in the format of zwave.xml, merged on top
+    /// of the built-in ones, e.g., newer or proprietary command classes.
+    #[arg(long, global = true, env = "ZNIFF_CC_XML")]
+    cc_xml: Option<PathBuf>,
+
+    /// Frame header definitions in the format of FrameDefinition.xml, merged
+    /// on top of the built-in ones.
+    #[arg(long, global = true, env = "ZNIFF_FRAME_DEF")]
+    frame_def: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -70,6 +101,114 @@ enum Commands {
         /// Input file, e.g., trace.zlf.
         #[arg(short, long)]
         trace: String,
+
+        /// List the attachments stored in the trace.
+        #[arg(long)]
+        attachments: bool,
+
+        /// Extract the attachments into this directory.
+        #[arg(long)]
+        extract_attachments: Option<String>,
+    },
+
+    /// Build or refresh the random-access index of a Z-Wave trace.
+    ///
+    /// The index is stored next to the trace, e.g., trace.zlf.idx, and lets
+    /// frames be fetched by number or time without reading the whole trace.
+    Index {
+        /// Input file, e.g., trace.zlf.
+        #[arg(short, long)]
+        trace: String,
+
+        /// Print the record holding this frame number, counting from 1.
+        #[arg(long)]
+        frame: Option<usize>,
+    },
+
+    /// Recover the readable records of a truncated or corrupted trace.
+    ///
+    /// Skips damaged records, reports the skipped byte ranges and writes the
+    /// remaining records, including attachments, to a new trace.
+    Repair {
+        /// Input file, e.g., broken.zlf.
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file, e.g., repaired.zlf.
+        #[arg(short, long)]
+        output: String,
+    },
+
+    /// Merge several traces into one, interleaving the records by timestamp.
+    ///
+    /// The header of the first trace is kept, with the comments of all traces.
+    Merge {
+        /// Input files, e.g., sniffer1.zlf sniffer2.zlf.
+        #[arg(short, long, num_args = 2.., required = true)]
+        input: Vec<String>,
+
+        /// Output file, e.g., merged.zlf.
+        #[arg(short, long)]
+        output: String,
+
+        /// Replace the merged header comments with this comment.
+        #[arg(long)]
+        comment: Option<String>,
+    },
+
+    /// Split a trace into parts, e.g., trace_001.zlf, trace_002.zlf, etc.
+    ///
+    /// Every part gets the header and attachments of the trace.
+    #[command(group(clap::ArgGroup::new("limit").required(true)))]
+    Split {
+        /// Input file, e.g., trace.zlf.
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file name, numbered for each part.
+        #[arg(short, long)]
+        output: String,
+
+        /// Maximum part size in bytes, e.g., 100M.
+        #[arg(long, group = "limit", value_parser = edit::parse_size)]
+        size: Option<u64>,
+
+        /// Maximum number of frames per part.
+        #[arg(long, group = "limit", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        frames: Option<usize>,
+
+        /// Maximum time span per part in seconds.
+        #[arg(long, group = "limit", value_parser = clap::value_parser!(u64).range(1..))]
+        duration: Option<u64>,
+    },
+
+    /// Cut a range of frames, by frame number or time, out of a trace.
+    ///
+    /// The output gets the header and attachments of the trace.
+    Slice {
+        /// Input file, e.g., trace.zlf.
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file, e.g., slice.zlf.
+        #[arg(short, long)]
+        output: String,
+
+        /// First frame to keep, counting from 1.
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        first: Option<usize>,
+
+        /// Last frame to keep.
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        last: Option<usize>,
+
+        /// Keep frames from this time, e.g., 2024-02-29T12:00:00Z.
+        #[arg(long)]
+        from: Option<zlf::types::ZlfTimestamp>,
+
+        /// Keep frames before this time, e.g., 2024-02-29T12:10:00Z.
+        #[arg(long)]
+        to: Option<zlf::types::ZlfTimestamp>,
     },
 
     /// Converts a trace from one format to another
@@ -92,167 +231,102 @@ enum Commands {
         /// Z-Wave region e.g., US, EU, etc.
         #[arg(long, value_parser = value_parser!(Region), required = true)]
         region: Region,
-    },
 
-    /// Parses a Z-Wave frame from a string input.
-    Parse {
-        /// String representing the Z-Wave frame
+        /// Save the captured frames to a ZLF file, e.g., capture.zlf.
         #[arg(long)]
-        input: String,
+        save: Option<String>,
+
+        /// Comment stored in the header of the saved ZLF file, e.g., a test-case ID.
+        #[arg(long, requires = "save")]
+        comment: Option<String>,
     },
-}
 
-struct Zniffer {
-    port: Box<dyn SerialPort>,
-    region: Region,
-    parser: zniffer_parser::Parser,
-}
+    /// Emulate a Zniffer device that replays the frames of a trace.
+    ///
+    /// The device is attached to a pseudo-terminal, whose path is printed,
+    /// or served over TCP, e.g., to test the `server` command without hardware.
+    Emulate {
+        /// Frames to replay, e.g., trace.zlf or trace.json from `convert`.
+        #[arg(short, long)]
+        trace: String,
 
-impl Zniffer {
-    fn new(port: Box<dyn SerialPort>, region: Region) -> Self {
-        Zniffer {
-            port,
-            region,
-            parser: zniffer_parser::Parser::new()
-        }
-    }
+        /// Serve the device over TCP on this address instead, e.g., 127.0.0.1:4906.
+        #[arg(long)]
+        tcp: Option<String>,
 
-    fn get_version(&mut self) -> Result<Vec<u8>, std::io::Error> {
-        let msg: Vec<u8> = vec![
-            0x23, // SOF
-            0x01, // Command: 0x01 = Version
-            0x00, // Length
-        ];
+        /// Delay in milliseconds between frames.
+        #[arg(long, default_value_t = 100)]
+        interval: u64,
 
-        self.port.write_all(&msg)?;
+        /// Start over with the first frame after the last one.
+        #[arg(long)]
+        repeat: bool,
 
-        let mut value: u8 = 0;
-        loop {
-            match self.port.read(std::slice::from_mut(&mut value)) {
-                Ok(bytes_read) => {
-                    println!("Received {:?} bytes", bytes_read);
-                    print!("0x{:02X} ", value);
-                    println!();
-                    match self.parser.parse(value) {
-                        zniffer_parser::ParserResult::ValidCommand { id, payload } => {
-                            println!("Got command ID {:?} with payload {:?}", id, payload);
-                            return Ok(payload);
-                        },
-                        zniffer_parser::ParserResult::ValidFrame { frame: _ } => {
-                            // This should not happen since we're expecting a response to Get Version.
-                        },
-                        zniffer_parser::ParserResult::IncompleteFrame => {
-                            // Continue parsing.
-                        },
-                        zniffer_parser::ParserResult::InvalidFrame => {
-                            println!("Invalid frame received.");
-                        },
-                    }
-                },
-                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
-                    self.parser.timeout();
-                    break;
-                }
-                Err(e) => {
-                    eprintln!("Error reading from serial port: {:?}", e);
-                    break;
-                }
-            }
-        }
-        Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "Timed out waiting for version response"))
-    }
+        /// Send a malformed frame before every Nth frame.
+        #[arg(long)]
+        malformed_every: Option<usize>,
+    },
 
-    fn set_region(&mut self) -> Result<(), std::io::Error>  {
-        let msg: Vec<u8> = vec![
-            0x23, // SOF
-            0x02, // Set region
-            0x01, // Length
-            self.region as u8,
-        ];
-        self.port.write_all(&msg)?;
-        Ok(())
-    }
+    /// Query a Zniffer device, e.g., its version and the supported regions.
+    Device {
+        /// Serial port e.g., COM3 or /dev/ttyUSB0
+        #[arg(long)]
+        serial: String,
 
-    fn start(&mut self) -> Vec<u8> {
-        let msg: Vec<u8> = vec![
-            0x23, // SOF
-            0x04, // Start
-            0x00, // Length
-        ];
-        let send_result = self.port.write_all(&msg);
-
-        match send_result {
-            Ok(()) => println!("Write successful"),
-            Err(e) => eprintln!("Write failed: {}", e),
-        }
+        /// Switch the device and the serial port to this baud rate, e.g., 115200.
+        #[arg(long)]
+        baud_rate: Option<u32>,
 
-        let mut buffer: Vec<u8> = vec![0; 128];
-        let mut response_length: usize = 0;
-        loop {
-            match self.port.read(buffer.as_mut_slice()) {
-                Ok(bytes_read) => {
-                    //println!("Received {:?} bytes", bytes_read);
-                    //for byte in &buffer[..bytes_read] {
-                    //    print!("0x{:02X} ", byte);
-                    //}
-                    //println!();
-                    response_length = bytes_read;
-                    print_hex(&buffer[0..response_length].to_vec());
-                    // TODO: Add frame parsing so we can exit when a valid frame is received.
-                },
-                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
-                    println!("Timed out waiting for response");
-                    break;
-                }
-                Err(e) => {
-                    eprintln!("Error reading from serial port: {:?}", e);
-                    break;
-                }
-            }
-        }
-        buffer[0..response_length].to_vec()
-    }
+        /// Select this Long Range channel configuration.
+        #[arg(long)]
+        lr_channel_config: Option<u8>,
 
-    fn get_frames(&mut self) -> Result<Frame, bool> {
-        let mut value: u8 = 0;
-        loop {
-            // TODO: Do we need to read data from the serial port into a ring buffer to avoid
-            // dropping frame 2 of 2 that might have been read from the serial port at once?
-            match self.port.read(std::slice::from_mut(&mut value)) {
-                Ok(_bytes_read) => {
-                    match self.parser.parse(value) {
-                        zniffer_parser::ParserResult::ValidCommand { id: _, payload: _ } => {
-                            // This should not happen as we do not expect
-                            // unsolicited commands from the zniffer device.
-                        },
-                        zniffer_parser::ParserResult::ValidFrame { frame } => {
-                            return Ok(frame);
-                        },
-                        zniffer_parser::ParserResult::IncompleteFrame => {
-                            // Continue parsing.
-                        },
-                        zniffer_parser::ParserResult::InvalidFrame => {
-                            println!("Invalid frame received.");
-                        },
-                    }
-                },
-                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
-                    self.parser.timeout();
-                }
-                Err(e) => {
-                    eprintln!("Error reading from serial port: {:?}", e);
-                    return Err(false);
-                }
-            }
-        }
-    }
+        /// Stop a capture left running, e.g., by an interrupted server.
+        #[arg(long)]
+        stop: bool,
+    },
+
+    /// Parses a Z-Wave frame from a string input.
+    Parse {
+        /// String representing the Z-Wave frame. Repeat for several frames,
+        /// e.g., a Version Command Class Report followed by commands of the
+        /// same node, which then decode with the reported version.
+        #[arg(long, required = true)]
+        input: Vec<String>,
+
+        /// Decode commands with this command class version instead of the
+        /// highest or the reported one.
+        #[arg(long)]
+        cc_version: Option<u8>,
+
+        /// Region the frame was received in, selects the MAC header layout.
+        #[arg(long, value_parser = value_parser!(Region), default_value = "EU")]
+        region: Region,
+
+        /// The frame was received on a Long Range channel.
+        #[arg(long)]
+        long_range: bool,
+    },
 }
 
-fn print_hex(vec: &Vec<u8>) {
-    for byte in vec {
-        print!("0x{:02X} ", byte);
-    }
-    println!(); // newline at the end
+/// A frame as written to JSON by the `convert` command.
+#[derive(Serialize)]
+struct JsonFrame {
+    /// Capture time from the ZLF record (RFC 3339, UTC).
+    time: String,
+    /// ZLF record properties byte.
+    properties: u8,
+    #[serde(flatten)]
+    frame: Frame,
+    /// Destination node ID of a beam or beam start.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beam_target: Option<u16>,
+    /// Time since the beam start, for a beam stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beam_duration_ms: Option<u16>,
+    /// Headers and command of a data frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decoded: Option<DecodedFrame>,
 }
 
 async fn handle_client(
@@ -263,6 +337,10 @@ async fn handle_client(
         tokio::select! {
             result = rx.recv() => {
                 match result {
+                    Ok(frame) if frame.frame_type == zlf::FrameType::BeamStop => {
+                        // PTI captures hold every beam fragment instead of a
+                        // start and stop, so there is nothing to send.
+                    }
                     Ok(mut frame) => {
                         match frame.to_pti_vector()
                         {
@@ -319,66 +397,179 @@ async fn handle_client(
     }
 }
 
-async fn run(port_name: String, region: &Region) {
-    let baud_rate = 230_400;
-
+fn open_zniffer(port_name: &str) -> Result<Zniffer<SerialStream>, ZnifferError> {
     println!("Connecting to {}", port_name);
-    let port = serialport::new(port_name, baud_rate)
-        .timeout(Duration::from_millis(500))
-        .open()
-        .expect("Failed to open port");
+    let port = device::open_serial(port_name, device::DEFAULT_BAUD_RATE)?;
+    Ok(Zniffer::new(port))
+}
+
+fn print_version(version: &Version) {
+    println!(
+        "Version: {}.{:02} (chip type 0x{:02X}, chip version 0x{:02X})",
+        version.major, version.minor, version.chip_type, version.chip_version,
+    );
+}
+
+async fn query_device(port_name: &str, baud_rate: Option<u32>, lr_channel_config: Option<u8>, stop: bool) -> Result<(), ZnifferError> {
+    let mut zniffer = open_zniffer(port_name)?;
+
+    if stop {
+        zniffer.stop().await?;
+        println!("Capture stopped.");
+    }
+
+    print_version(&zniffer.get_version().await?);
+
+    if let Some(bits_per_second) = baud_rate {
+        match BaudRate::try_from(bits_per_second) {
+            Ok(baud_rate) => zniffer.set_baud_rate(baud_rate).await?,
+            Err(()) => eprintln!("Unsupported baud rate: {}", bits_per_second),
+        }
+    }
+    if let Some(baud_rate) = zniffer.baud_rate() {
+        println!("Baud rate: {}", baud_rate);
+    }
+
+    println!("Supported regions:");
+    for code in zniffer.get_regions().await? {
+        let region = match Region::try_from(code) {
+            Ok(region) => format!("{:?}", region),
+            Err(_) => "unknown".to_string(),
+        };
+        match zniffer.get_region_name(code).await {
+            Ok((name, channels)) => println!("  {:3}  {:<8} {} ({} channels)", code, region, name, channels),
+            Err(e) => println!("  {:3}  {:<8} ({})", code, region, e),
+        }
+    }
+
+    if let Some(config) = lr_channel_config {
+        zniffer.set_lr_channel_config(config).await?;
+    }
+    match zniffer.get_lr_channel_configs().await {
+        Ok((current, supported)) => {
+            println!("Long Range channel configuration: {} (supported: {:?})", current, supported);
+        },
+        Err(e) => {
+            eprintln!("Failed to get the Long Range channel configurations: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the frames of a ZLF trace or of a JSON file written by `convert`.
+fn load_frames(path: &str) -> Result<Vec<Frame>, Box<dyn std::error::Error>> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("")
+        .to_lowercase();
+
+    if extension == "json" {
+        let json = std::fs::read_to_string(path)?;
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    let file = std::fs::File::open(path)?;
+    let mut frames = Vec::new();
+    for record in zlf::ZlfReader::new(file)?.into_frames() {
+        if let zlf::ZlfRecord::Data(data_frame) = record? {
+            frames.push(data_frame.frame);
+        }
+    }
+    Ok(frames)
+}
+
+async fn emulate(emulator: Emulator, tcp: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(addr) = tcp {
+        let listener = TcpListener::bind(addr).await?;
+        println!("Emulated Zniffer listening on {}", addr);
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            println!("Host connected: {addr}");
+            let emulator = emulator.clone();
+            tokio::spawn(async move {
+                if let Err(e) = emulator.run(stream).await {
+                    eprintln!("Emulated Zniffer failed: {}", e);
+                }
+            });
+        }
+    }
 
-    let mut zniffer = Zniffer::new(port, *region);
+    #[cfg(unix)]
+    {
+        let (mut port, path) = device::pseudo_terminal()?;
+        println!("Emulated Zniffer attached to {}", path);
+        loop {
+            match emulator.clone().run(&mut port).await {
+                // Reading fails while no host has the pseudo-terminal open.
+                Err(ZnifferError::Io(_)) => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+                result => return Ok(result?),
+            }
+        }
+    }
 
-    match zniffer.get_version() {
+    #[cfg(not(unix))]
+    {
+        Err("Pseudo-terminals are not supported on this platform, use --tcp".into())
+    }
+}
+
+async fn run(port_name: String, region: &Region, save: Option<String>, comment: Option<String>) {
+    let mut zniffer = match open_zniffer(&port_name) {
+        Ok(zniffer) => zniffer,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", port_name, e);
+            return;
+        }
+    };
+
+    match zniffer.get_version().await {
         Ok(version) => {
-            println!("Got version:");
-            print_hex(&version);
+            print_version(&version);
         },
         Err(e) => {
-            eprintln!("Failed to get the version: {:?}", e);
+            eprintln!("Failed to get the version: {}", e);
         }
     }
 
-    match zniffer.set_region() {
+    match zniffer.set_region(*region).await {
         Ok(()) => {
             // Don't do anything.
         },
         Err(e) => {
-            eprintln!("Failed to set the region: {:?}", e);
+            eprintln!("Failed to set the region: {}", e);
         }
     }
 
-    let _ = zniffer.start();
-
-    let (tx, _rx) = broadcast::channel(16);
-
-    let tx_clone = tx.clone();
-    // Spawn a blocking task to read frames since the serial port read is blocking (not async - yet).
-    tokio::task::spawn_blocking(move || {
-        loop {
-            match zniffer.get_frames() {
-                Ok(frame) => {
-                    match tx_clone.send(frame) {
-                        Ok(_) => {
-                            // Successfully sent
-                        },
-                        Err(e) => {
-                            println!("Failed to send frame to channel: {:?}", e);
-                            return;
-                        }
-                    }
-                },
-                Err(true) => {
-                    panic!("Should never happen!");
+    let mut zlf_writer = match save {
+        Some(path) => {
+            let file = match std::fs::File::create(&path) {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("Failed to create '{}': {:?}", path, e);
+                    return;
+                }
+            };
+            let header = zlf::ZlfHeader::with_comment(comment.as_deref().unwrap_or(""));
+            match zlf::ZlfWriter::with_header(file, &header) {
+                Ok(w) => {
+                    println!("Saving frames to {}", path);
+                    Some(w)
                 },
-                Err(false) => {
-                    println!("Failed to get frame!");
+                Err(e) => {
+                    eprintln!("Failed to write ZLF header: {:?}", e);
                     return;
                 }
             }
-        }
-    });
+        },
+        None => None,
+    };
+
+    if let Err(e) = zniffer.start().await {
+        eprintln!("Failed to start the capture: {}", e);
+        return;
+    }
 
     // PC Zniffer PTI default port is 4905
     let listener = match TcpListener::bind("0.0.0.0:4905").await {
@@ -390,21 +581,56 @@ async fn run(port_name: String, region: &Region) {
     };
     println!("Server listening on port 4905");
 
-    loop {
-        let (stream, addr) = match listener.accept().await {
-            Ok(sa) => sa,
-            Err(e) => {
-                eprintln!("Failed to accept connection: {:?}", e);
-                continue;
-            }
-        };
-        println!("Client connected: {addr}");
-
-        let mut rx = tx.subscribe();
+    let (tx, _rx) = broadcast::channel(16);
 
-        tokio::spawn(async move {
-            handle_client(stream, &mut rx).await;
-        });
+    loop {
+        tokio::select! {
+            result = zniffer.next_frame() => {
+                let frame = match result {
+                    Ok(frame) => frame,
+                    Err(ZnifferError::Stopped) => {
+                        println!("Capture stopped.");
+                        return;
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to get frame: {}", e);
+                        return;
+                    }
+                };
+                if let Some(writer) = zlf_writer.as_mut() {
+                    let timestamp = zlf::types::ZlfTimestamp::now();
+                    if let Err(e) = writer.write_zniffer(timestamp, &frame.to_zniffer_vector()) {
+                        eprintln!("Failed to save frame: {:?}", e);
+                    }
+                }
+                if tx.send(frame).is_err() {
+                    // No clients connected.
+                }
+            },
+            accepted = listener.accept() => {
+                let (stream, addr) = match accepted {
+                    Ok(sa) => sa,
+                    Err(e) => {
+                        eprintln!("Failed to accept connection: {:?}", e);
+                        continue;
+                    }
+                };
+                println!("Client connected: {addr}");
+
+                let mut rx = tx.subscribe();
+
+                tokio::spawn(async move {
+                    handle_client(stream, &mut rx).await;
+                });
+            },
+            _ = tokio::signal::ctrl_c() => {
+                println!("Stopping the capture...");
+                if let Err(e) = zniffer.stop().await {
+                    eprintln!("Failed to stop the capture: {}", e);
+                }
+                return;
+            },
+        }
     }
 }
 
@@ -451,39 +677,176 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             Ok(())
         },
-        Commands::Inspect { trace } => {
+        Commands::Inspect { trace, attachments, extract_attachments } => {
             println!("Inspecting trace '{}'", trace);
 
             // Open the ZLF file
             let file = std::fs::File::open(trace)?;
-            let mut reader = zlf::ZlfReader::new(file)?;
+            let reader = zlf::ZlfReader::new(file)?;
+
+            let header = reader.header();
+            println!("Version: {}", header.version);
+            println!("Encoding: {}", header.encoding);
+            println!("Comment: {}", header.comment);
+            if header.reserved.iter().any(|b| *b != 0) {
+                println!("Reserved header bytes: {}", hex::encode_upper(&header.reserved));
+            }
 
-            let mut attachment_counter = 0;
+            let mut attachment_records: Vec<zlf::Attachment> = Vec::new();
             let mut data_frame_counter = 0;
+            let mut checksum_error_counter = 0;
+            let mut beam_frame_counter = 0;
+            let mut beam_tracker = BeamTracker::new();
+            let mut beams: Vec<Beam> = Vec::new();
+            let mut command_counter = 0;
             let mut other_frame_counter = 0;
 
             println!("Found:");
 
-            reader.read_frames(|record| {
-                match record {
-                    zlf::ZlfRecord::Attachment => {
-                        attachment_counter += 1;
+            for record in reader.into_frames() {
+                match record? {
+                    zlf::ZlfRecord::Attachment(attachment) => {
+                        attachment_records.push(attachment);
                     },
-                    zlf::ZlfRecord::Data(_data_frame) => {
-                        data_frame_counter += 1;
+                    zlf::ZlfRecord::Data(data_frame) => {
+                        if data_frame.frame.frame_type == zlf::FrameType::Data {
+                            data_frame_counter += 1;
+                            if !data_frame.frame.checksum_ok() {
+                                checksum_error_counter += 1;
+                            }
+                        } else {
+                            beam_frame_counter += 1;
+                        }
+                        beams.extend(beam_tracker.push(&data_frame.frame));
                     },
-                    zlf::ZlfRecord::Other(_raw_frame) => {
-                        other_frame_counter += 1;
+                    zlf::ZlfRecord::Other(raw_frame) => {
+                        if raw_frame.frame_type == zlf::FrameType::Command {
+                            command_counter += 1;
+                        } else {
+                            other_frame_counter += 1;
+                        }
                     },
                 }
-            })?;
+            }
 
-            println!("{} attachment record(s)", attachment_counter);
-            println!("{} data frame(s)", data_frame_counter);
+            println!("{} attachment record(s)", attachment_records.len());
+            println!("{} data frame(s), {} with checksum errors", data_frame_counter, checksum_error_counter);
+            println!("{} beam frame(s)", beam_frame_counter);
+            println!("{} wake-up beam(s)", beams.len());
+            for beam in &beams {
+                match beam.target {
+                    Some(target) => print!("  Beam to node {}", target),
+                    None => print!("  Beam"),
+                }
+                println!(" on channel {}, {} ms", beam.channel, beam.duration_ms);
+            }
+            println!("{} command(s)", command_counter);
             println!("{} other frame(s)", other_frame_counter);
 
+            if *attachments {
+                for (i, attachment) in attachment_records.iter().enumerate() {
+                    print!("Attachment {}: {} {} bytes, ", i + 1, attachment.timestamp, attachment.data.len());
+                    match &attachment.content {
+                        zlf::AttachmentContent::Xml { root, .. } => println!("XML <{}>", root),
+                        zlf::AttachmentContent::Text(text) => {
+                            println!("text \"{}\"", text.lines().next().unwrap_or(""));
+                        },
+                        zlf::AttachmentContent::Zip(entries) => {
+                            println!("ZIP archive with {} entries", entries.len());
+                            for entry in entries {
+                                println!("  {} ({} bytes)", entry.name, entry.size);
+                            }
+                        },
+                        zlf::AttachmentContent::Binary => println!("binary"),
+                    }
+                }
+            }
+
+            if let Some(dir) = extract_attachments {
+                std::fs::create_dir_all(dir)?;
+                for (i, attachment) in attachment_records.iter().enumerate() {
+                    let path = std::path::Path::new(dir)
+                        .join(format!("attachment_{}.{}", i + 1, attachment.file_extension()));
+                    std::fs::write(&path, &attachment.data)?;
+                    println!("Extracted {}", path.display());
+                }
+            }
+
+            Ok(())
+        },
+        Commands::Index { trace, frame } => {
+            let index = zlf::ZlfIndex::open(trace)?;
+            index.persist(trace)?;
+            println!("Index: {}", zlf::ZlfIndex::sidecar_path(trace).display());
+            println!("{} record(s)", index.len());
+            println!("{} frame record(s)", index.frame_count());
+            if let (Some(first), Some(last)) = (index.records().first(), index.records().last()) {
+                println!("First record: {}", first.timestamp);
+                println!("Last record: {}", last.timestamp);
+            }
+
+            if let Some(number) = frame {
+                let Some(entry) = number.checked_sub(1).and_then(|n| index.frame(n)) else {
+                    return Err(format!("frame {} not found", number).into());
+                };
+                let mut reader = zlf::ZlfReader::new(std::fs::File::open(trace)?)?;
+                reader.seek_record(entry)?;
+                if let Some(zlf::ZlfRecord::Other(raw_frame)) = reader.next()? {
+                    println!(
+                        "Frame {}: {} {:?} at offset {}: {}",
+                        number,
+                        raw_frame.timestamp,
+                        raw_frame.api_type,
+                        entry.offset,
+                        hex::encode_upper(&raw_frame.payload),
+                    );
+                }
+            }
+
             Ok(())
         },
+        Commands::Repair { input, output } => {
+            println!("Repairing '{}' into '{}'", input, output);
+
+            let file = std::fs::File::open(input)?;
+            let mut reader = zlf::ZlfReader::new(io::BufReader::new(file))?.lenient(true);
+            let file = std::fs::File::create(output)?;
+            let mut writer = zlf::ZlfWriter::with_header(io::BufWriter::new(file), reader.header())?;
+
+            let mut record_counter = 0;
+            while let Some(record) = reader.next()? {
+                writer.write_record(&record)?;
+                record_counter += 1;
+            }
+            writer.finish()?;
+
+            for range in reader.skipped() {
+                println!("Skipped bytes {}..{} ({} bytes)", range.start, range.end, range.end - range.start);
+            }
+            println!("Repair complete. Wrote {} record(s) to {}", record_counter, output);
+
+            Ok(())
+        },
+        Commands::Merge { input, output, comment } => {
+            edit::merge(input, output, comment.as_deref())
+        },
+        Commands::Split { input, output, size, frames, duration } => {
+            let limit = match (size, frames, duration) {
+                (Some(size), _, _) => edit::SplitLimit::Size(*size),
+                (_, Some(frames), _) => edit::SplitLimit::Frames(*frames),
+                (_, _, Some(duration)) => edit::SplitLimit::Duration(*duration),
+                _ => unreachable!("clap requires one limit"),
+            };
+            edit::split(input, output, limit)
+        },
+        Commands::Slice { input, output, first, last, from, to } => {
+            let range = if from.is_some() || to.is_some() {
+                edit::SliceRange::Time(*from, *to)
+            } else {
+                edit::SliceRange::Frames(*first, *last)
+            };
+            edit::slice(input, output, range)
+        },
         Commands::Convert { input, output } => {
             println!("Converting '{}' to '{}'", input, output);
 
@@ -514,50 +877,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // Open the ZLF file
             let file = std::fs::File::open(input)?;
-            let mut reader = zlf::ZlfReader::new(file)?;
-
-            // Collect raw Z-Wave frames (just the payloads)
-            let mut raw_frames: Vec<Frame> = Vec::new();
-
-            // We need the zniffer parser to parse the payload of each
-            // ZLF record.
-            let mut parser = zniffer_parser::Parser::new();
-
-            reader.read_frames(|record| {
-                match record {
-                    zlf::ZlfRecord::Other(raw_frame) => {
-                        for byte in raw_frame.payload.iter() {
-                            let result = parser.parse(*byte);
-
-                            match result {
-                                zniffer_parser::ParserResult::ValidFrame { frame } => {
-
-                                    raw_frames.push(frame);
-                                    /*
-                                    let db_frame = DbFrame {
-                                        id: frame_id as i64, // You can generate or extract an ID for the frame
-                                        timestamp: frame.timestamp as i64, // You can extract this from the frame if needed
-                                        speed: frame.speed,     // You can extract this from the frame if needed
-                                        rssi: frame.rssi as i8,      // You can extract this from the frame if needed
-                                        channel: frame.channel,   // You can extract this from the frame if needed
-                                        home_id: 0x12345678, // Example home_id, replace with actual value if available
-                                        src_node_id: 1, // Example src_node_id, replace with actual value if available
-                                        dst_node_id: 2, // Example dst_node_id, replace with actual value if available
-                                        payload: frame.payload.clone(), // Use the raw payload from the parsed frame
-                                    };
-                                    */
-                                },
-                                _ => {
-                                    // Don't care about other parser results than a valid frame for now.
-                                },
-                            }
-                        }
-                    },
-                    _ => {
-                        // Ignore other frame types for now.
-                    }
-                }
+            let reader = zlf::ZlfReader::new(file)?;
+
+            // Collect the decoded Z-Wave frames.
+            let mut raw_frames: Vec<JsonFrame> = Vec::new();
+            let mut beams = BeamTracker::new();
+            let definitions = Definitions::load(cli.cc_xml.as_deref(), cli.frame_def.as_deref()).map_err(|e| {
+                eprintln!("Error: {}", e);
+                "Failed to load the definitions"
             })?;
+            let mut decoder = Decoder::with_definitions(&definitions);
+
+            for record in reader.into_frames() {
+                if let zlf::ZlfRecord::Data(data_frame) = record? {
+                    let beam = beams.push(&data_frame.frame);
+                    raw_frames.push(JsonFrame {
+                        time: data_frame.timestamp.to_string(),
+                        properties: data_frame.properties.0,
+                        beam_target: data_frame.frame.beam_target(),
+                        beam_duration_ms: beam.map(|beam| beam.duration_ms),
+                        decoded: (data_frame.frame.frame_type == FrameType::Data)
+                            .then(|| decoder.decode_frame(&data_frame.frame)),
+                        frame: data_frame.frame,
+                    });
+                }
+            }
 
             // Serialize to JSON
             let json = serde_json::to_string_pretty(&raw_frames)?;
@@ -569,16 +913,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             Ok(())
         }
-        Commands::Server { serial, region} => {
+        Commands::Server { serial, region, save, comment } => {
             println!("Region: {:?}", region);
-            run(serial.to_string(), region).await;
+            run(serial.to_string(), region, save.clone(), comment.clone()).await;
+            Ok(())
+        },
+        Commands::Emulate { trace, tcp, interval, repeat, malformed_every } => {
+            let frames = load_frames(trace)?;
+            println!("Replaying {} frames from {}", frames.len(), trace);
+            let mut emulator = Emulator::new(frames)
+                .interval(std::time::Duration::from_millis(*interval))
+                .repeat(*repeat);
+            if let Some(n) = malformed_every {
+                emulator = emulator.malformed_every(*n);
+            }
+            emulate(emulator, tcp.as_deref()).await
+        },
+        Commands::Device { serial, baud_rate, lr_channel_config, stop } => {
+            query_device(serial, *baud_rate, *lr_channel_config, *stop).await?;
             Ok(())
         },
-        Commands::Parse { input } => {
-            let fd = frame_definition::parse_xml();
-            let zwc = xml::parse_xml();
-            let zw_parser: ZwParser = ZwParser::new(&fd, &zwc);
-            zw_parser.parse_str(&input);
+        Commands::Parse { input, cc_version, region, long_range } => {
+            let definitions = Definitions::load(cli.cc_xml.as_deref(), cli.frame_def.as_deref()).map_err(|e| {
+                eprintln!("Error: {}", e);
+                "Failed to load the definitions"
+            })?;
+            let mut zw_parser = ZwParser::new(&definitions, *cc_version);
+            let layout = match long_range {
+                true => Layout::LongRange,
+                false => Layout::for_region(*region),
+            };
+            for input in input {
+                zw_parser.parse_str(input, layout);
+            }
             Ok(())
         }
     }
@@ -0,0 +1,182 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Turns zwave.xml and FrameDefinition.xml into static tables, so the decoder
+//! does not parse them at run time, and XML that no longer matches the
+//! structs of the decoder or fails their validation fails the build.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+#[path = "src/decoder/xml.rs"]
+mod xml;
+
+#[allow(dead_code)]
+#[path = "src/decoder/frame_definition.rs"]
+mod frame_definition;
+
+use frame_definition::*;
+use xml::*;
+
+/// Writes a value as a Rust expression of the same type.
+trait Literal {
+    fn literal(&self, out: &mut String);
+}
+
+impl Literal for Str {
+    fn literal(&self, out: &mut String) {
+        // Debug escapes strings the way Rust literals do.
+        out.push_str(&format!("Cow::Borrowed({:?})", self));
+    }
+}
+
+impl<T: Literal + Clone> Literal for List<T> {
+    fn literal(&self, out: &mut String) {
+        out.push_str("Cow::Borrowed(&[\n");
+        for item in self.iter() {
+            item.literal(out);
+            out.push_str(",\n");
+        }
+        out.push_str("])");
+    }
+}
+
+impl<T: Literal> Literal for Option<T> {
+    fn literal(&self, out: &mut String) {
+        match self {
+            Some(value) => {
+                out.push_str("Some(");
+                value.literal(out);
+                out.push(')');
+            },
+            None => out.push_str("None"),
+        }
+    }
+}
+
+/// Implement `Literal` for structs. The fields are destructured, so a field
+/// missing here fails the build.
+macro_rules! literal_structs {
+    ($($name:ident { $($field:ident),* $(,)? })*) => {
+        $(
+            impl Literal for $name {
+                fn literal(&self, out: &mut String) {
+                    let $name { $($field),* } = self;
+                    out.push_str(concat!(stringify!($name), " {"));
+                    $(
+                        out.push_str(concat!(stringify!($field), ": "));
+                        $field.literal(out);
+                        out.push_str(", ");
+                    )*
+                    out.push('}');
+                }
+            }
+        )*
+    };
+}
+
+/// Implement `Literal` for enums of newtype variants.
+macro_rules! literal_enums {
+    ($($name:ident { $($variant:ident),* $(,)? })*) => {
+        $(
+            impl Literal for $name {
+                fn literal(&self, out: &mut String) {
+                    match self {
+                        $(
+                            $name::$variant(value) => {
+                                out.push_str(concat!(stringify!($name), "::", stringify!($variant), "("));
+                                value.literal(out);
+                                out.push(')');
+                            },
+                        )*
+                    }
+                }
+            }
+        )*
+    };
+}
+
+literal_structs! {
+    ZwClasses { version, text, bas_dev, gen_dev, cmd_class }
+    BasDev { key, name, help, comment }
+    GenDev { key, name, help, comment, text, spec_dev }
+    SpecDev { key, name, help, comment }
+    CmdClass { key, version, name, help, comment, text, cmd }
+    Cmd { key, name, help, support_mode, comment, cmd_mask, text, children }
+    CmdClassCmdParam {
+        key, name, param_type, encaptype, comment, optionalmask, optionaloffs, cmd_mask, skipfield, text, children,
+    }
+    CmdParamVariant { paramoffs, sizemask, is_ascii, sizechange, sizeoffs }
+    CmdParamBitflag { key, flagname, flagmask }
+    CmdParamBitfield { key, fieldname, fieldmask, shifter }
+    CmdParamFieldenum { key, fieldname, fieldmask, shifter, text, fieldenum }
+    CmdParamFieldenumValue { key, value }
+    CmdParamBitmask { key, paramoffs, lenmask, lenoffs, len }
+    CmdParamConst { key, flagname, flagmask }
+    Arrayattrib { key, len, is_ascii }
+    MultiArray { text, paramdescloc, bitflag }
+    Paramdescloc { key, param, paramdesc, paramstart }
+    CmdVariantGroup {
+        key, name, param_offs, sizemask, sizeoffs, optionalmask, optionaloffs, moretofollowmask, moretofollowoffs,
+        text, children,
+    }
+
+    FrameDefinition { text, radio_frequency, base_header, header, define_set, header_filter }
+    RadioFrequency { name, code, base_header, text }
+    BaseHeader {
+        key, name, base_header_text, text, param, home_id, source, header_type, is_ltx, sequence_number, destination,
+    }
+    FrameDefinitionBaseHeaderParam { order, name, param_text, param_type, bits, text, param }
+    BaseHeaderParamParam { order, name, text, param_type, bits, defines }
+    HomeId { home_id_ref }
+    Source { source_ref }
+    HeaderType { header_type_ref }
+    IsLtx { is_ltx_ref }
+    SequenceNumber { sequence_number_ref }
+    BaseHeaderDestination { destination_ref }
+    Header {
+        key, name, header_text, base_header_key, is_ack, is_error, is_multicast, is_routed, text, repeaters, hops,
+        destination, param, validation,
+    }
+    Repeaters { repeaters_ref, size_ref, is_bitmask, size_correction }
+    Hops { hops_ref }
+    HeaderDestination { text, destinations, destination }
+    Destinations { destinations_ref, size_ref, is_bitmask, size_correction }
+    DestinationDestination { destination_ref }
+    FrameDefinitionHeaderParam { order, name, param_text, param_type, bits, opt_ref, size_ref, text, param }
+    HeaderParamParam { order, name, text, param_type, bits, defines }
+    Validation { param_name, param_hex_value }
+    DefineSet { name, text, define }
+    Define { key, name, text }
+    HeaderFilter { order, header_filter_text, text, header_codes }
+    HeaderCodes { text, header_code }
+}
+
+literal_enums! {
+    CmdClassCmdChild { Param, VariantGroup }
+    CmdParamChild { Variant, Bitflag, Bitfield, Fieldenum, Bitmask, Const, Arrayattrib, MultiArray }
+}
+
+fn main() {
+    const ZWAVE_XML: &str = "src/decoder/zwave.xml";
+    const FRAME_DEFINITION_XML: &str = "src/decoder/FrameDefinition.xml";
+    for path in [ZWAVE_XML, FRAME_DEFINITION_XML, "src/decoder/xml.rs", "src/decoder/frame_definition.rs"] {
+        println!("cargo::rerun-if-changed={}", path);
+    }
+
+    let read = |path| fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    let classes = xml::parse(&read(ZWAVE_XML)).unwrap_or_else(|e| panic!("{}: {}", ZWAVE_XML, e));
+    let frames = frame_definition::parse(&read(FRAME_DEFINITION_XML))
+        .unwrap_or_else(|e| panic!("{}: {}", FRAME_DEFINITION_XML, e));
+    classes.validate().unwrap_or_else(|e| panic!("{}: {}", ZWAVE_XML, e));
+    frames.validate().unwrap_or_else(|e| panic!("{}: {}", FRAME_DEFINITION_XML, e));
+
+    let mut out = String::from("pub static CLASSES: ZwClasses = ");
+    classes.literal(&mut out);
+    out.push_str(";\n\npub static FRAMES: FrameDefinition = ");
+    frames.literal(&mut out);
+    out.push_str(";\n");
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("tables.rs");
+    fs::write(&path, out).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+}
@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Wake-up beams.
+//!
+//! A node wakes up a FLiRS (frequently listening) node by sending a train of
+//! beam fragments for about a second before the actual frame. A Zniffer
+//! reports the train as a `BeamStart` frame followed by a `BeamStop` frame,
+//! while a PTI capture holds a `Beam` frame for every fragment.
+//!
+//! Every fragment starts with the beam tag followed by the destination node
+//! ID, which is one byte for classic Z-Wave and 12 bits for Long Range.
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::types::{
+    Frame,
+    FrameType,
+    Region,
+    SPEED_LONG_RANGE,
+};
+
+/// The first byte of a beam fragment.
+pub const BEAM_TAG: u8 = 0x55;
+
+/// A wake-up beam from start to stop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beam {
+    pub region: Region,
+    pub channel: u8,
+    pub speed: u8,
+    pub rssi: u8,
+    /// Destination node ID, if the beam start holds a fragment.
+    pub target: Option<u16>,
+    /// Zniffer timestamp of the beam start.
+    pub timestamp: u16,
+    /// Time between beam start and stop in milliseconds.
+    pub duration_ms: u16,
+}
+
+impl Frame {
+    /// Destination node ID of a beam frame.
+    pub fn beam_target(&self) -> Option<u16> {
+        if !matches!(self.frame_type, FrameType::Beam | FrameType::BeamStart) {
+            return None;
+        }
+        match self.payload.as_slice() {
+            [BEAM_TAG, high, low, ..] if self.speed == SPEED_LONG_RANGE => {
+                Some(u16::from_be_bytes([*high, *low]) >> 4)
+            },
+            [BEAM_TAG, node_id, ..] if self.speed != SPEED_LONG_RANGE => Some(*node_id as u16),
+            _ => None,
+        }
+    }
+}
+
+/// Pairs `BeamStart` and `BeamStop` frames into beams.
+#[derive(Debug, Default)]
+pub struct BeamTracker {
+    start: Option<Frame>,
+}
+
+impl BeamTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the next frame of a trace. Returns the completed beam for a
+    /// `BeamStop` following a `BeamStart`.
+    pub fn push(&mut self, frame: &Frame) -> Option<Beam> {
+        match frame.frame_type {
+            FrameType::BeamStart => {
+                self.start = Some(frame.clone());
+                None
+            },
+            FrameType::BeamStop => {
+                let start = self.start.take()?;
+                Some(Beam {
+                    region: start.region,
+                    channel: start.channel,
+                    speed: start.speed,
+                    rssi: start.rssi,
+                    target: start.beam_target(),
+                    timestamp: start.timestamp,
+                    // The Zniffer timestamp is a free-running 16 bit counter.
+                    duration_ms: frame.timestamp.wrapping_sub(start.timestamp),
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beam_tracker() {
+        let start = Frame {
+            frame_type: FrameType::BeamStart,
+            timestamp: 0xFF00,
+            payload: vec![BEAM_TAG, 0x05],
+            ..Default::default()
+        };
+        let stop = Frame {
+            frame_type: FrameType::BeamStop,
+            timestamp: 0x0250,
+            payload: vec![0x00, 0x2C],
+            ..Default::default()
+        };
+
+        let mut tracker = BeamTracker::new();
+        assert_eq!(tracker.push(&stop), None);
+        assert_eq!(tracker.push(&start), None);
+        let beam = tracker.push(&stop).unwrap();
+        assert_eq!(beam.target, Some(5));
+        assert_eq!(beam.duration_ms, 0x350);
+
+        let long_range = Frame {
+            speed: SPEED_LONG_RANGE,
+            payload: vec![BEAM_TAG, 0x10, 0x1A],
+            ..start
+        };
+        assert_eq!(long_range.beam_target(), Some(0x101));
+    }
+}
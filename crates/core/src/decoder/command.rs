@@ -0,0 +1,856 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Decoder of command class payloads, driven by the command definitions of
+//! zwave.xml.
+//!
+//! Parameter sizes and presence refer to earlier parameters by key, e.g., a
+//! VARIANT with `paramoffs="1"` takes its size from parameter 0x01, masked
+//! with `sizemask`. Inside a variant group, keys refer to the parameters of
+//! the group, and keys with bit 7 set to the parameters of the command.
+//! `paramoffs="255"` means the rest of the payload.
+use std::collections::HashMap;
+use std::fmt;
+use serde::Serialize;
+use super::xml::{
+    CmdClass,
+    CmdClassCmdChild,
+    CmdClassCmdParam,
+    CmdParamChild,
+    CmdVariantGroup,
+    ZwClasses,
+    parse_number,
+};
+
+/// A decoded command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedCommand {
+    /// Position of the command class in the MPDU.
+    pub offset: usize,
+    pub class_key: u8,
+    pub class: String,
+    pub version: u8,
+    pub command_key: Option<u8>,
+    /// None if the class has no command with the key.
+    pub command: Option<String>,
+    pub params: Vec<DecodedParam>,
+    /// Bytes following the last parameter.
+    pub extra: Vec<u8>,
+    /// The payload ended within a parameter.
+    pub truncated: bool,
+}
+
+/// A parameter, a field of a parameter or an instance of a variant group.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedParam {
+    pub name: String,
+    /// Position in the MPDU.
+    pub offset: usize,
+    /// Number of bytes, 0 for fields of a parameter.
+    pub len: usize,
+    pub value: ParamValue,
+    /// Bitflags, bitfields and fieldenums of a parameter, or the parameters
+    /// of a variant group.
+    pub children: Vec<DecodedParam>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ParamValue {
+    /// Variant groups and markers.
+    None,
+    Number(u32),
+    Flag(bool),
+    /// A value with a name, e.g., a CONST, a fieldenum or a command class.
+    Enum {
+        value: u32,
+        name: Option<String>,
+    },
+    Bytes(Vec<u8>),
+    Text(String),
+    List(Vec<ParamValue>),
+    /// Set bits of a BITMASK, with the name of the bit, if any.
+    Bits(Vec<(u32, Option<String>)>),
+    /// An encapsulated command.
+    Command(Box<DecodedCommand>),
+}
+
+/// Parse a key or an offset, 0 if invalid. Definitions are validated when
+/// loaded, see `ZwClasses::validate()`.
+fn number(s: &str) -> u32 {
+    parse_number(s).unwrap_or(0)
+}
+
+/// Bytes as upper case hex, e.g., "01FF".
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// The bits of `value` selected by `mask`, shifted down to bit 0.
+fn masked(value: u32, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    (value & mask) >> mask.trailing_zeros()
+}
+
+/// Values of the parameters decoded so far, by key.
+#[derive(Default)]
+struct Scope<'s> {
+    values: Vec<(u32, u32)>,
+    /// Parameters of the command, for keys with bit 7 set in a variant
+    /// group.
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl Scope<'_> {
+    fn get(&self, key: u32) -> Option<u32> {
+        match self.outer {
+            Some(outer) if key & 0x80 != 0 => outer.get(key & 0x7F),
+            _ => self.values.iter().rev().find(|(k, _)| *k == key).map(|(_, v)| *v),
+        }
+    }
+}
+
+/// State of decoding one command.
+struct Cursor<'d> {
+    data: &'d [u8],
+    /// Position of `data` in the MPDU.
+    base: usize,
+    pos: usize,
+    truncated: bool,
+    /// Last command class and command referenced by CMD_CLASS_REF and
+    /// CMD_REF, for CMD_DATA.
+    class_ref: Option<u8>,
+    command_ref: Option<u8>,
+    /// Last generic device class, for SPEC_DEV_REF.
+    generic_ref: Option<u8>,
+}
+
+impl<'d> Cursor<'d> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Position in the MPDU.
+    fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    /// Take `n` bytes, or the rest of the payload if it is shorter.
+    fn take(&mut self, n: usize) -> &'d [u8] {
+        if n > self.remaining() {
+            self.truncated = true;
+        }
+        let end = (self.pos + n).min(self.data.len());
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        bytes
+    }
+}
+
+/// Decodes commands with the command class versions of a node.
+pub(super) struct CommandDecoder<'a> {
+    pub classes: &'a ZwClasses,
+    /// Command class versions by node ID and command class.
+    pub versions: &'a HashMap<(u16, u8), u8>,
+    /// Version of all command classes, instead of the versions of the node.
+    pub version: Option<u8>,
+    /// The sending node.
+    pub node_id: u16,
+    /// The destination node, e.g., of a Set or Get from the controller, whose
+    /// versions apply if those of the sending node are unknown.
+    pub dst_node_id: Option<u16>,
+}
+
+impl<'a> CommandDecoder<'a> {
+    /// The definitions of a command class for `version`, i.e., the highest
+    /// defined version up to `version`, or the lowest if all are higher.
+    /// The highest version if `version` is None.
+    fn class(&self, key: u8, version: Option<u8>) -> Option<&'a CmdClass> {
+        let classes = || self.classes.cmd_class.iter().filter(move |class| number(&class.key) == key as u32);
+        let version_of = |class: &&CmdClass| number(&class.version);
+        match version {
+            None => classes().max_by_key(version_of),
+            Some(version) => classes()
+                .filter(|class| version_of(class) <= version as u32)
+                .max_by_key(version_of)
+                .or_else(|| classes().min_by_key(version_of)),
+        }
+    }
+
+    /// Decode a payload at `offset` in the MPDU, starting with the command
+    /// class. Returns None for an empty payload or an unknown command class.
+    pub(super) fn decode(&self, payload: &[u8], offset: usize) -> Option<DecodedCommand> {
+        let key = *payload.first()?;
+        let version = self.version.or_else(|| {
+            let node_version = |node_id| self.versions.get(&(node_id, key)).copied();
+            node_version(self.node_id).or_else(|| self.dst_node_id.and_then(node_version))
+        });
+        let class = self.class(key, version)?;
+        Some(self.decode_class(class, payload, offset))
+    }
+
+    fn decode_class(&self, class: &CmdClass, payload: &[u8], offset: usize) -> DecodedCommand {
+        let mut decoded = DecodedCommand {
+            offset,
+            class_key: number(&class.key) as u8,
+            class: class.help.to_string(),
+            version: number(&class.version) as u8,
+            command_key: payload.get(1).copied(),
+            command: None,
+            params: Vec::new(),
+            extra: payload.get(2..).unwrap_or_default().to_vec(),
+            truncated: false,
+        };
+        let Some(command_key) = decoded.command_key else {
+            return decoded;
+        };
+        let Some(cmd) = class.cmd.as_deref().into_iter().flatten().find(|cmd| match &cmd.cmd_mask {
+            Some(mask) => command_key as u32 & number(mask) == number(&cmd.key),
+            None => command_key as u32 == number(&cmd.key),
+        }) else {
+            return decoded;
+        };
+
+        let mut cursor = Cursor {
+            data: payload,
+            base: offset,
+            pos: 2,
+            truncated: false,
+            class_ref: None,
+            command_ref: None,
+            generic_ref: None,
+        };
+        let mut scope = Scope::default();
+        decoded.command = Some(cmd.help.to_string());
+        decoded.params = self.decode_children(cmd.children.as_deref().into_iter().flatten(), &mut cursor, &mut scope);
+        decoded.extra = payload[cursor.pos..].to_vec();
+        decoded.truncated = cursor.truncated;
+        decoded
+    }
+
+    fn decode_children<'c>(
+        &self,
+        children: impl Iterator<Item = &'c CmdClassCmdChild> + Clone,
+        cursor: &mut Cursor,
+        scope: &mut Scope,
+    ) -> Vec<DecodedParam> {
+        let mut params = Vec::new();
+        let mut rest = children;
+        while let Some(child) = rest.next() {
+            if cursor.truncated {
+                break;
+            }
+            // A VARIANT of unknown size ends at a following MARKER.
+            let marker_follows = matches!(
+                rest.clone().next(),
+                Some(CmdClassCmdChild::Param(p)) if p.param_type == "MARKER"
+            );
+            match child {
+                CmdClassCmdChild::Param(p) => {
+                    if !present(p.optionaloffs.as_deref(), p.optionalmask.as_deref(), scope) {
+                        continue;
+                    }
+                    if let Some(mask) = &p.cmd_mask {
+                        // Bits of the command byte, not a byte of its own.
+                        let value = masked(cursor.data[1] as u32, number(mask));
+                        scope.values.push((number(&p.key), value));
+                        params.push(DecodedParam {
+                            name: p.name.to_string(),
+                            offset: cursor.base + 1,
+                            len: 1,
+                            value: ParamValue::Number(value),
+                            children: fields(p, value, cursor.base + 1),
+                        });
+                        continue;
+                    }
+                    if cursor.remaining() == 0 {
+                        // Parameters added by later versions are missing.
+                        break;
+                    }
+                    if p.param_type == "MARKER" && cursor.data[cursor.pos] != marker_value(p) {
+                        continue;
+                    }
+                    let param = self.decode_param(p, cursor, scope, marker_follows);
+                    params.push(param);
+                },
+                CmdClassCmdChild::VariantGroup(group) => {
+                    params.extend(self.decode_group(group, cursor, scope));
+                },
+            }
+        }
+        params
+    }
+
+    fn decode_group(&self, group: &CmdVariantGroup, cursor: &mut Cursor, scope: &Scope) -> Vec<DecodedParam> {
+        if !present(group.optionaloffs.as_deref(), group.optionalmask.as_deref(), scope) {
+            return Vec::new();
+        }
+        let count = match number(&group.param_offs) {
+            0xFF => usize::MAX,
+            offs => masked(scope.get(offs).unwrap_or(0), number(&group.sizemask)) as usize,
+        };
+        let more_to_follow = group.moretofollowoffs.as_deref().zip(group.moretofollowmask.as_deref());
+
+        let mut instances = Vec::new();
+        for i in 0..count {
+            if cursor.remaining() == 0 || cursor.truncated {
+                break;
+            }
+            let start = cursor.pos;
+            let mut group_scope = Scope { values: Vec::new(), outer: Some(scope) };
+            let children = self.decode_children(group.children.iter(), cursor, &mut group_scope);
+            instances.push(DecodedParam {
+                name: format!("{} {}", group.name, i + 1),
+                offset: cursor.base + start,
+                len: cursor.pos - start,
+                value: ParamValue::None,
+                children,
+            });
+            if cursor.pos == start {
+                break;
+            }
+            if let Some((offs, mask)) = more_to_follow
+                && group_scope.get(number(offs)).unwrap_or(0) & number(mask) == 0
+            {
+                break;
+            }
+        }
+        instances
+    }
+
+    fn decode_param(&self, p: &CmdClassCmdParam, cursor: &mut Cursor, scope: &mut Scope, marker_follows: bool) -> DecodedParam {
+        let start = cursor.pos;
+        let offset = cursor.offset();
+        let encaptype = p.encaptype.as_deref();
+        let (value, children) = match p.param_type.as_ref() {
+            "VARIANT" => {
+                let variant = p.children.iter().find_map(|c| match c {
+                    CmdParamChild::Variant(v) => Some(v),
+                    _ => None,
+                });
+                let (size, ascii) = match variant {
+                    Some(v) => {
+                        let change = v.sizechange.as_deref().and_then(|s| s.trim().parse::<i64>().ok()).unwrap_or(0);
+                        let size = match number(&v.paramoffs) {
+                            0xFF if marker_follows => {
+                                let rest = &cursor.data[cursor.pos..];
+                                rest.iter().position(|b| *b == 0).unwrap_or(rest.len()) as i64
+                            },
+                            0xFF => cursor.remaining() as i64 + change,
+                            offs => masked(scope.get(offs).unwrap_or(0), number(&v.sizemask)) as i64 + change,
+                        };
+                        (size.max(0) as usize, v.is_ascii.as_deref() == Some("true"))
+                    },
+                    None => (cursor.remaining(), false),
+                };
+                let bytes = cursor.take(size);
+                (self.decode_bytes(bytes, offset, encaptype, ascii, cursor), Vec::new())
+            },
+            "ARRAY" => {
+                let attrib = p.children.iter().find_map(|c| match c {
+                    CmdParamChild::Arrayattrib(a) => Some(a),
+                    _ => None,
+                });
+                // The length is a maximum, e.g., for names.
+                let size = attrib.map_or(usize::MAX, |a| number(&a.len) as usize).min(cursor.remaining());
+                let ascii = attrib.and_then(|a| a.is_ascii.as_deref()) == Some("true");
+                let bytes = cursor.take(size);
+                (self.decode_bytes(bytes, offset, encaptype, ascii, cursor), Vec::new())
+            },
+            "BITMASK" => {
+                let bitmask = p.children.iter().find_map(|c| match c {
+                    CmdParamChild::Bitmask(b) => Some(b),
+                    _ => None,
+                });
+                let size = match bitmask {
+                    Some(b) => match (number(&b.paramoffs), &b.len) {
+                        (0xFF, Some(len)) => number(len) as usize,
+                        (0xFF, None) => cursor.remaining(),
+                        (offs, _) => masked(scope.get(offs).unwrap_or(0), number(&b.lenmask)) as usize,
+                    },
+                    None => cursor.remaining(),
+                };
+                let bytes = cursor.take(size);
+                let bits = bytes
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, byte)| (0..8).filter(move |bit| byte & (1 << bit) != 0).map(move |bit| (i * 8 + bit) as u32))
+                    .map(|bit| match encaptype {
+                        // Bit 0 addresses node 1.
+                        Some("NODE_NUMBER") => (bit + 1, None),
+                        _ => (bit, bitflag_name(p, bit)),
+                    })
+                    .collect();
+                (ParamValue::Bits(bits), Vec::new())
+            },
+            "MULTI_ARRAY" => {
+                let value = cursor.take(1).first().copied().unwrap_or(0) as u32;
+                // The first table names the parameter selecting one of the
+                // other tables, whose bitflags are keyed by its value.
+                let selector = p.children.iter().find_map(|c| match c {
+                    CmdParamChild::MultiArray(m) => m.paramdescloc.as_ref(),
+                    _ => None,
+                });
+                let selected = selector.and_then(|s| scope.get(number(&s.param)));
+                let name = p
+                    .children
+                    .iter()
+                    .filter_map(|c| match c {
+                        CmdParamChild::MultiArray(m) => m.bitflag.as_deref(),
+                        _ => None,
+                    })
+                    .flatten()
+                    .find(|flag| Some(number(&flag.key)) == selected && number(&flag.flagmask) == value)
+                    .map(|flag| flag.flagname.to_string());
+                (ParamValue::Enum { value, name }, Vec::new())
+            },
+            param_type => {
+                let size = match param_type {
+                    "WORD" => 2,
+                    "BIT_24" => 3,
+                    "DWORD" => 4,
+                    _ => 1,
+                };
+                let value = cursor.take(size).iter().fold(0u32, |value, byte| value << 8 | *byte as u32);
+                let value = match (param_type, encaptype) {
+                    ("CONST" | "MARKER", _) => {
+                        let name = p.children.iter().find_map(|c| match c {
+                            CmdParamChild::Const(c) if number(&c.flagmask) == value => Some(c.flagname.to_string()),
+                            _ => None,
+                        });
+                        ParamValue::Enum { value, name }
+                    },
+                    (_, Some(encaptype)) if encaptype != "NODE_NUMBER" => self.reference(encaptype, value, cursor),
+                    _ => ParamValue::Number(value),
+                };
+                let number_value = match value {
+                    ParamValue::Number(v) | ParamValue::Enum { value: v, .. } => v,
+                    _ => 0,
+                };
+                (value, fields(p, number_value, offset))
+            },
+        };
+        if let ParamValue::Number(v) | ParamValue::Enum { value: v, .. } = value {
+            scope.values.push((number(&p.key), v));
+        }
+        DecodedParam {
+            name: p.name.to_string(),
+            offset,
+            len: cursor.pos - start,
+            value,
+            children,
+        }
+    }
+
+    /// Decode the bytes of a VARIANT or an ARRAY at `offset` in the MPDU.
+    fn decode_bytes(&self, bytes: &[u8], offset: usize, encaptype: Option<&str>, ascii: bool, cursor: &mut Cursor) -> ParamValue {
+        match encaptype {
+            _ if ascii => ParamValue::Text(String::from_utf8_lossy(bytes).into_owned()),
+            Some("NODE_NUMBER") => ParamValue::List(bytes.iter().map(|b| ParamValue::Number(*b as u32)).collect()),
+            Some("CMD_CLASS_REF") => {
+                ParamValue::List(bytes.iter().map(|b| self.reference("CMD_CLASS_REF", *b as u32, cursor)).collect())
+            },
+            Some("CMD_ENCAP") => match self.decode(bytes, offset) {
+                Some(command) => ParamValue::Command(Box::new(command)),
+                None => ParamValue::Bytes(bytes.to_vec()),
+            },
+            Some("CMD_DATA") => {
+                let command = cursor.class_ref.zip(cursor.command_ref).and_then(|(class, command)| {
+                    let payload = [&[class, command], bytes].concat();
+                    // The data follows the command class and command.
+                    self.decode(&payload, offset.saturating_sub(2)).filter(|decoded| decoded.command.is_some())
+                });
+                match command {
+                    Some(command) => ParamValue::Command(Box::new(command)),
+                    None => ParamValue::Bytes(bytes.to_vec()),
+                }
+            },
+            _ => ParamValue::Bytes(bytes.to_vec()),
+        }
+    }
+
+    /// Name a command class, command or device class referenced by a
+    /// parameter.
+    fn reference(&self, encaptype: &str, value: u32, cursor: &mut Cursor) -> ParamValue {
+        let name = match encaptype {
+            "CMD_CLASS_REF" => {
+                cursor.class_ref = Some(value as u8);
+                self.class(value as u8, None).map(|class| class.help.to_string())
+            },
+            "CMD_REF" => {
+                cursor.command_ref = Some(value as u8);
+                cursor
+                    .class_ref
+                    .and_then(|class| self.class(class, None))
+                    .and_then(|class| class.cmd.as_deref().into_iter().flatten().find(|cmd| number(&cmd.key) == value))
+                    .map(|cmd| cmd.help.to_string())
+            },
+            "BAS_DEV_REF" => self.classes.bas_dev.iter().find(|d| number(&d.key) == value).map(|d| d.help.to_string()),
+            "GEN_DEV_REF" => {
+                cursor.generic_ref = Some(value as u8);
+                self.classes.gen_dev.iter().find(|d| number(&d.key) == value).map(|d| d.help.to_string())
+            },
+            "SPEC_DEV_REF" => self
+                .classes
+                .gen_dev
+                .iter()
+                .find(|d| Some(number(&d.key)) == cursor.generic_ref.map(u32::from))
+                .and_then(|d| d.spec_dev.iter().find(|s| number(&s.key) == value))
+                .map(|s| s.help.to_string()),
+            _ => None,
+        };
+        ParamValue::Enum { value, name }
+    }
+}
+
+/// Whether an optional parameter or variant group is present.
+fn present(optionaloffs: Option<&str>, optionalmask: Option<&str>, scope: &Scope) -> bool {
+    match (optionaloffs, optionalmask) {
+        (Some(offs), Some(mask)) => scope.get(number(offs)).unwrap_or(0) & number(mask) != 0,
+        _ => true,
+    }
+}
+
+/// The constant of a MARKER, 0 unless defined otherwise.
+fn marker_value(p: &CmdClassCmdParam) -> u8 {
+    p.children
+        .iter()
+        .find_map(|c| match c {
+            CmdParamChild::Const(c) => Some(number(&c.flagmask) as u8),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// The name of a bit of a BITMASK. Bitflags of a BITMASK hold the bit number
+/// in `flagmask`.
+fn bitflag_name(p: &CmdClassCmdParam, bit: u32) -> Option<String> {
+    p.children.iter().find_map(|c| match c {
+        CmdParamChild::Bitflag(flag) if number(&flag.flagmask) == bit => Some(flag.flagname.to_string()),
+        _ => None,
+    })
+}
+
+/// Bitflags, bitfields and fieldenums of a parameter of `value`.
+fn fields(p: &CmdClassCmdParam, value: u32, offset: usize) -> Vec<DecodedParam> {
+    p.children
+        .iter()
+        .filter_map(|c| {
+            let (name, value) = match c {
+                CmdParamChild::Bitflag(flag) => {
+                    (&flag.flagname, ParamValue::Flag(value & number(&flag.flagmask) != 0))
+                },
+                CmdParamChild::Bitfield(field) => (&field.fieldname, ParamValue::Number(masked(value, number(&field.fieldmask)))),
+                CmdParamChild::Fieldenum(field) => {
+                    let value = masked(value, number(&field.fieldmask));
+                    // Values are numbered from 0 unless they have a key.
+                    let name = field
+                        .fieldenum
+                        .iter()
+                        .enumerate()
+                        .find(|(i, e)| e.key.as_deref().map_or(*i as u32, number) == value)
+                        .map(|(_, e)| e.value.to_string());
+                    (&field.fieldname, ParamValue::Enum { value, name })
+                },
+                _ => return None,
+            };
+            Some(DecodedParam { name: name.to_string(), offset, len: 0, value, children: Vec::new() })
+        })
+        .collect()
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamValue::None => Ok(()),
+            ParamValue::Number(value) => write!(f, "{}", value),
+            ParamValue::Flag(value) => write!(f, "{}", value),
+            ParamValue::Enum { value, name: Some(name) } => write!(f, "0x{:02X} ({})", value, name),
+            ParamValue::Enum { value, name: None } => write!(f, "0x{:02X}", value),
+            ParamValue::Bytes(bytes) => write!(f, "{}", hex(bytes)),
+            ParamValue::Text(text) => write!(f, "{:?}", text),
+            ParamValue::List(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            },
+            ParamValue::Bits(bits) => {
+                let bits: Vec<String> = bits
+                    .iter()
+                    .map(|(bit, name)| match name {
+                        Some(name) => name.clone(),
+                        None => bit.to_string(),
+                    })
+                    .collect();
+                write!(f, "[{}]", bits.join(", "))
+            },
+            ParamValue::Command(command) => write!(f, "{}", command.title()),
+        }
+    }
+}
+
+impl DecodedCommand {
+    /// The command class and command, e.g., "Command Class Basic: Basic Set".
+    pub fn title(&self) -> String {
+        match &self.command {
+            Some(command) => format!("{} (Version {}): {}", self.class, self.version, command),
+            None => match self.command_key {
+                Some(key) => format!("{} (Version {}): unknown command 0x{:02X}", self.class, self.version, key),
+                None => format!("{} (Version {})", self.class, self.version),
+            },
+        }
+    }
+
+    pub(super) fn fmt_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        for param in &self.params {
+            param.fmt_indented(f, indent)?;
+        }
+        if !self.extra.is_empty() {
+            writeln!(f, "{:indent$}Extra bytes: {}", "", hex(&self.extra))?;
+        }
+        if self.truncated {
+            writeln!(f, "{:indent$}Truncated", "")?;
+        }
+        Ok(())
+    }
+}
+
+impl DecodedParam {
+    pub(super) fn fmt_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match &self.value {
+            // Byte-sized parameters in hex, fields of a parameter in decimal.
+            ParamValue::Number(value) if self.len > 0 => {
+                writeln!(f, "{:indent$}{}: 0x{:0width$X}", "", self.name, value, width = self.len * 2)?
+            },
+            ParamValue::None => writeln!(f, "{:indent$}{}", "", self.name)?,
+            value => writeln!(f, "{:indent$}{}: {}", "", self.name, value)?,
+        }
+        for child in &self.children {
+            child.fmt_indented(f, indent + 2)?;
+        }
+        if let ParamValue::Command(command) = &self.value {
+            command.fmt_indented(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DecodedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.title())?;
+        self.fmt_indented(f, 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{
+        tables::CLASSES,
+        xml,
+    };
+
+    /// Decode a payload with the highest version of the built-in definitions.
+    fn decode(payload: &[u8]) -> DecodedCommand {
+        decode_with(&CLASSES, payload)
+    }
+
+    fn decode_with(classes: &ZwClasses, payload: &[u8]) -> DecodedCommand {
+        let versions = HashMap::new();
+        let decoder = CommandDecoder { classes, versions: &versions, version: None, node_id: 1, dst_node_id: None };
+        decoder.decode(payload, 0).unwrap()
+    }
+
+    fn param<'p>(params: &'p [DecodedParam], name: &str) -> &'p DecodedParam {
+        params.iter().find(|p| p.name == name).unwrap_or_else(|| panic!("no parameter {}", name))
+    }
+
+    fn named(value: u32, name: &str) -> ParamValue {
+        ParamValue::Enum { value, name: Some(name.to_string()) }
+    }
+
+    #[test]
+    fn test_numbers() {
+        let command = decode(&[
+            0x3F, 0x02, 0x41, 0x42, 0x00, 0x01, 0xE2, 0x40, 0x40, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x05,
+            0x00, 0x03, 0xD2, 0x0A,
+        ]);
+        assert_eq!(command.command.as_deref(), Some("Prepayment Balance Report"));
+        let balance = param(&command.params, "Balance Value");
+        assert_eq!((balance.offset, balance.len, &balance.value), (4, 4, &ParamValue::Number(123_456)));
+        assert_eq!(param(&command.params, "Emer Credit").value, ParamValue::Number(5));
+        let currency = param(&command.params, "Currency");
+        assert_eq!((currency.offset, currency.len, &currency.value), (18, 3, &ParamValue::Number(978)));
+        assert_eq!(param(&command.params, "Debt Recovery Percentage").value, ParamValue::Number(10));
+        assert!(command.extra.is_empty());
+        assert!(!command.truncated);
+
+        // Association Group Info Report: the Event Code of each group.
+        let command = decode(&[0x59, 0x04, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x12, 0x34]);
+        let event_code = param(&command.params[1].children, "Event Code");
+        assert_eq!((event_code.len, &event_code.value), (2, &ParamValue::Number(0x1234)));
+    }
+
+    #[test]
+    fn test_fields() {
+        // Wednesday, 14:30
+        let command = decode(&[0x81, 0x06, 0x6E, 0x1E]);
+        let level = param(&command.params, "Level");
+        assert_eq!(level.value, ParamValue::Number(0x6E));
+        assert_eq!(param(&level.children, "Hour").value, ParamValue::Number(14));
+        // Weekdays are keyed from 1.
+        assert_eq!(param(&level.children, "Weekday").value, named(3, "Wednesday"));
+        assert_eq!(param(&command.params, "Minute").value, ParamValue::Number(30));
+
+        // Session 5, with status updates.
+        let command = decode(&[0x6C, 0x01, 0x85, 0x00]);
+        let properties = param(&command.params, "Properties1");
+        assert_eq!(param(&properties.children, "Session ID").value, ParamValue::Number(5));
+        assert_eq!(param(&properties.children, "Reserved").value, ParamValue::Flag(false));
+        assert_eq!(param(&properties.children, "Status Updates").value, ParamValue::Flag(true));
+    }
+
+    #[test]
+    fn test_variants() {
+        // Two extensions, the length of each including its length and type,
+        // followed by the ciphertext.
+        let command = decode(&[
+            0x9F, 0x03, 0x07, 0x01, 0x03, 0xC1, 0xAA, 0x04, 0x02, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF,
+        ]);
+        assert_eq!(command.command.as_deref(), Some("S2 Message Encapsulation"));
+        let extensions: Vec<_> = command.params.iter().filter(|p| p.name.starts_with("vg1")).collect();
+        assert_eq!(extensions.len(), 2);
+        assert_eq!((extensions[0].offset, extensions[0].len), (4, 3));
+        assert_eq!(param(&extensions[0].children, "Extension").value, ParamValue::Bytes(vec![0xAA]));
+        let properties = param(&extensions[0].children, "Properties1");
+        assert_eq!(param(&properties.children, "More to follow").value, ParamValue::Flag(true));
+        assert_eq!(param(&extensions[1].children, "Extension").value, ParamValue::Bytes(vec![0xBB, 0xCC]));
+        let ciphertext = param(&command.params, "CCM Ciphertext Object");
+        assert_eq!((ciphertext.offset, &ciphertext.value), (11, &ParamValue::Bytes(vec![0xDD, 0xEE, 0xFF])));
+
+        // Without the extension flag, no extensions are present.
+        let command = decode(&[0x9F, 0x03, 0x07, 0x00, 0x03, 0xC1]);
+        assert!(!command.params.iter().any(|p| p.name.starts_with("vg1")));
+        assert_eq!(param(&command.params, "CCM Ciphertext Object").value, ParamValue::Bytes(vec![0x03, 0xC1]));
+    }
+
+    #[test]
+    fn test_marker() {
+        // Nodes 2 and 3, then end point 1 of node 4 and end point 2 of node 5.
+        let command = decode(&[0x8E, 0x04, 0x01, 0x02, 0x03, 0x00, 0x04, 0x01, 0x05, 0x02]);
+        let nodes = param(&command.params, "Node ID");
+        assert_eq!(nodes.value, ParamValue::List(vec![ParamValue::Number(2), ParamValue::Number(3)]));
+        assert_eq!(param(&command.params, "Marker").value, named(0, "Marker"));
+        let groups: Vec<_> = command.params.iter().filter(|p| p.name.starts_with("vg ")).collect();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(param(&groups[1].children, "Multi Channel Node ID").value, ParamValue::Number(5));
+        let properties = param(&groups[1].children, "Properties1");
+        assert_eq!(param(&properties.children, "End Point").value, ParamValue::Number(2));
+
+        // The marker may be left out if no end points follow.
+        let command = decode(&[0x8E, 0x04, 0x01, 0x02, 0x03]);
+        assert_eq!(command.params.len(), 2);
+        assert_eq!(param(&command.params, "Node ID").len, 2);
+    }
+
+    #[test]
+    fn test_text() {
+        let command = decode(&[0x7B, 0x03, 0x01, 0x00, b'L', b'i', b'f', b'e', b'l', b'i', b'n', b'e']);
+        let name = param(&command.params, "Grouping Name");
+        assert_eq!((name.offset, name.len, &name.value), (4, 8, &ParamValue::Text("Lifeline".to_string())));
+    }
+
+    #[test]
+    fn test_bitmask() {
+        let command = decode(&[0x9F, 0x05, 0x00, 0x02, 0x01, 0x83]);
+        let keys = param(&command.params, "Requested Keys");
+        assert_eq!(
+            keys.value,
+            ParamValue::Bits(vec![
+                (0, Some("Unauthenticated".to_string())),
+                (1, Some("Authenticated".to_string())),
+                (7, Some("S0".to_string())),
+            ])
+        );
+
+        // zwave.xml has no node bitmask, so define one.
+        let mut classes = CLASSES.clone();
+        classes.merge(&xml::parse(r#"<zw_classes>
+            <cmd_class key="0xEE" version="1" name="COMMAND_CLASS_ACME" help="Command Class Acme">
+                <cmd key="0x01" name="ACME_NODES" help="Acme Nodes">
+                    <param key="0x00" name="Nodes" type="BITMASK" encaptype="NODE_NUMBER">
+                        <bitmask key="0x00" paramoffs="255" lenmask="0x00"/>
+                    </param>
+                </cmd>
+            </cmd_class>
+        </zw_classes>"#).unwrap());
+        let command = decode_with(&classes, &[0xEE, 0x01, 0x05, 0x80]);
+        // Bit 0 is node 1.
+        let bits = vec![(1, None), (3, None), (16, None)];
+        assert_eq!(param(&command.params, "Nodes").value, ParamValue::Bits(bits));
+    }
+
+    #[test]
+    fn test_multi_array() {
+        // Group 2 with the control profile, key 1.
+        let command = decode(&[0x59, 0x04, 0x01, 0x02, 0x00, 0x20, 0x01, 0x00, 0x00, 0x00]);
+        let group = &command.params[1];
+        assert_eq!(group.name, "vg1 1");
+        assert_eq!(param(&group.children, "Profile1").value, named(0x20, "AGI_PROFILE_CONTROL"));
+        assert_eq!(param(&group.children, "Profile2").value, named(0x01, "AGI_CONTROL_KEY01"));
+
+        // The general profile selects another table.
+        let command = decode(&[0x59, 0x04, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(param(&command.params[1].children, "Profile2").value, named(0x01, "AGI_GENERAL_LIFELINE"));
+    }
+
+    #[test]
+    fn test_references() {
+        // Basic Set, followed by the checksum.
+        let command = decode(&[0x56, 0x01, 0x20, 0x01, 0xFF, 0x12, 0x34]);
+        assert_eq!(param(&command.params, "Command Class").value, named(0x20, "Command Class Basic"));
+        assert_eq!(param(&command.params, "Command").value, named(0x01, "Basic Set"));
+        let data = param(&command.params, "Data");
+        assert_eq!((data.offset, data.len), (4, 1));
+        let ParamValue::Command(encapsulated) = &data.value else {
+            panic!("expected a command, got {:?}", data.value);
+        };
+        assert_eq!(encapsulated.offset, 2);
+        assert_eq!(encapsulated.command.as_deref(), Some("Basic Set"));
+        assert_eq!(encapsulated.params[0].offset, 4);
+        assert_eq!(encapsulated.params[0].value, ParamValue::Number(0xFF));
+        assert_eq!(param(&command.params, "Checksum").value, ParamValue::Number(0x1234));
+
+        // Supervision Get of a Basic Set.
+        let command = decode(&[0x6C, 0x01, 0x85, 0x03, 0x20, 0x01, 0x63]);
+        let ParamValue::Command(encapsulated) = &param(&command.params, "Encapsulated Command").value else {
+            panic!("expected a command");
+        };
+        assert_eq!(encapsulated.offset, 4);
+        assert_eq!(encapsulated.title(), "Command Class Basic (Version 2): Basic Set");
+        assert_eq!(encapsulated.params[0].value, ParamValue::Number(0x63));
+
+        // Unknown command classes are kept as bytes.
+        let command = decode(&[0x6C, 0x01, 0x85, 0x02, 0xFE, 0x01]);
+        assert_eq!(param(&command.params, "Encapsulated Command").value, ParamValue::Bytes(vec![0xFE, 0x01]));
+    }
+
+    #[test]
+    fn test_truncated() {
+        // The payload ends within the Balance Value.
+        let command = decode(&[0x3F, 0x02, 0x41, 0x42, 0x00, 0x01]);
+        assert!(command.truncated);
+        assert_eq!(command.params.len(), 3);
+        let balance = param(&command.params, "Balance Value");
+        assert_eq!((balance.len, &balance.value), (2, &ParamValue::Number(1)));
+
+        // Missing trailing parameters are not truncated, e.g., those added
+        // by later versions.
+        let command = decode(&[0x3F, 0x02, 0x41]);
+        assert!(!command.truncated);
+        assert_eq!(command.params.len(), 1);
+
+        // The encapsulated command is shorter than its length.
+        let command = decode(&[0x6C, 0x01, 0x85, 0x05, 0x20, 0x01]);
+        assert!(command.truncated);
+    }
+}
@@ -0,0 +1,402 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use serde::{Deserialize, Serialize};
+use super::xml::{
+    check_number,
+    merge_list,
+    InvalidElement,
+    List,
+    Str,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameDefinition {
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    // Files merged on top of the built-in definitions may leave out any of
+    // the lists.
+    #[serde(rename = "RadioFrequency", default)]
+    pub radio_frequency: List<RadioFrequency>,
+    #[serde(rename = "BaseHeader", default)]
+    pub base_header: List<BaseHeader>,
+    #[serde(rename = "Header", default)]
+    pub header: List<Header>,
+    #[serde(rename = "DefineSet", default)]
+    pub define_set: List<DefineSet>,
+    #[serde(rename = "HeaderFilter", default)]
+    pub header_filter: List<HeaderFilter>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RadioFrequency {
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Code")]
+    pub code: Str,
+    #[serde(rename = "@BaseHeader")]
+    pub base_header: Str,
+    #[serde(rename = "@Text")]
+    pub text: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BaseHeader {
+    #[serde(rename = "@Key")]
+    pub key: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub base_header_text: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Param")]
+    pub param: List<FrameDefinitionBaseHeaderParam>,
+    #[serde(rename = "HomeId")]
+    pub home_id: HomeId,
+    #[serde(rename = "Source")]
+    pub source: Source,
+    #[serde(rename = "HeaderType")]
+    pub header_type: HeaderType,
+    #[serde(rename = "IsLTX")]
+    pub is_ltx: IsLtx,
+    #[serde(rename = "SequenceNumber")]
+    pub sequence_number: SequenceNumber,
+    #[serde(rename = "Destination")]
+    pub destination: Option<BaseHeaderDestination>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameDefinitionBaseHeaderParam {
+    #[serde(rename = "@Order")]
+    pub order: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub param_text: Str,
+    #[serde(rename = "@Type")]
+    pub param_type: Str,
+    #[serde(rename = "@Bits")]
+    pub bits: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Param")]
+    pub param: Option<List<BaseHeaderParamParam>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BaseHeaderParamParam {
+    #[serde(rename = "@Order")]
+    pub order: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub text: Str,
+    #[serde(rename = "@Type")]
+    pub param_type: Str,
+    #[serde(rename = "@Bits")]
+    pub bits: Str,
+    #[serde(rename = "@Defines")]
+    pub defines: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HomeId {
+    #[serde(rename = "@Ref")]
+    pub home_id_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Source {
+    #[serde(rename = "@Ref")]
+    pub source_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderType {
+    #[serde(rename = "@Ref")]
+    pub header_type_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IsLtx {
+    #[serde(rename = "@Ref")]
+    pub is_ltx_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SequenceNumber {
+    #[serde(rename = "@Ref")]
+    pub sequence_number_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BaseHeaderDestination {
+    #[serde(rename = "@Ref")]
+    pub destination_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    #[serde(rename = "@Key")]
+    pub key: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub header_text: Str,
+    #[serde(rename = "@BaseHeaderKey")]
+    pub base_header_key: Option<Str>,
+    #[serde(rename = "@IsAck")]
+    pub is_ack: Str,
+    #[serde(rename = "@IsError")]
+    pub is_error: Str,
+    #[serde(rename = "@IsMulticast")]
+    pub is_multicast: Str,
+    #[serde(rename = "@IsRouted")]
+    pub is_routed: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Repeaters")]
+    pub repeaters: Option<Repeaters>,
+    #[serde(rename = "Hops")]
+    pub hops: Option<Hops>,
+    #[serde(rename = "Destination")]
+    pub destination: Option<HeaderDestination>,
+    #[serde(rename = "Param")]
+    pub param: List<FrameDefinitionHeaderParam>,
+    #[serde(rename = "Validation")]
+    pub validation: Option<List<Validation>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Repeaters {
+    #[serde(rename = "@Ref")]
+    pub repeaters_ref: Str,
+    #[serde(rename = "@SizeRef")]
+    pub size_ref: Str,
+    #[serde(rename = "@IsBitmask")]
+    pub is_bitmask: Str,
+    #[serde(rename = "@SizeCorrection")]
+    pub size_correction: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hops {
+    #[serde(rename = "@Ref")]
+    pub hops_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderDestination {
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Destinations")]
+    pub destinations: Option<Destinations>,
+    #[serde(rename = "Destination")]
+    pub destination: Option<List<DestinationDestination>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Destinations {
+    #[serde(rename = "@Ref")]
+    pub destinations_ref: Str,
+    #[serde(rename = "@SizeRef")]
+    pub size_ref: Str,
+    #[serde(rename = "@IsBitmask")]
+    pub is_bitmask: Str,
+    #[serde(rename = "@SizeCorrection")]
+    pub size_correction: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DestinationDestination {
+    #[serde(rename = "@Ref")]
+    pub destination_ref: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameDefinitionHeaderParam {
+    #[serde(rename = "@Order")]
+    pub order: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub param_text: Str,
+    #[serde(rename = "@Type")]
+    pub param_type: Str,
+    #[serde(rename = "@Bits")]
+    pub bits: Str,
+    #[serde(rename = "@OptRef")]
+    pub opt_ref: Option<Str>,
+    #[serde(rename = "@SizeRef")]
+    pub size_ref: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Param")]
+    pub param: Option<List<HeaderParamParam>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderParamParam {
+    #[serde(rename = "@Order")]
+    pub order: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub text: Str,
+    #[serde(rename = "@Type")]
+    pub param_type: Str,
+    #[serde(rename = "@Bits")]
+    pub bits: Str,
+    #[serde(rename = "@Defines")]
+    pub defines: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Validation {
+    #[serde(rename = "@ParamName")]
+    pub param_name: Str,
+    #[serde(rename = "@ParamHexValue")]
+    pub param_hex_value: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DefineSet {
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "Define")]
+    pub define: List<Define>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Define {
+    #[serde(rename = "@Key")]
+    pub key: Str,
+    #[serde(rename = "@Name")]
+    pub name: Str,
+    #[serde(rename = "@Text")]
+    pub text: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderFilter {
+    #[serde(rename = "@Order")]
+    pub order: Str,
+    #[serde(rename = "@Text")]
+    pub header_filter_text: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "HeaderCodes")]
+    pub header_codes: HeaderCodes,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderCodes {
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "HeaderCode")]
+    pub header_code: List<Str>,
+}
+
+impl FrameDefinition {
+    /// Merge the definitions of `other` on top: elements replace those with
+    /// the same key, define sets those with the same name, and the others
+    /// are added.
+    pub fn merge(&mut self, other: &FrameDefinition) {
+        merge_list(&mut self.radio_frequency, &other.radio_frequency, |a, b| a.code == b.code);
+        merge_list(&mut self.base_header, &other.base_header, |a, b| a.key == b.key);
+        merge_list(&mut self.header, &other.header, |a, b| a.key == b.key);
+        merge_list(&mut self.define_set, &other.define_set, |a, b| a.name == b.name);
+        merge_list(&mut self.header_filter, &other.header_filter, |a, b| a.order == b.order);
+    }
+
+    /// Check the attributes the decoder relies on, e.g., that parameters
+    /// are whole bytes and that headers and defines refer to existing
+    /// elements.
+    pub fn validate(&self) -> Result<(), InvalidElement> {
+        for base_header in self.base_header.iter() {
+            let element = format!("BaseHeader {}", base_header.name);
+            check_number(&element, "Key", &base_header.key)?;
+            for p in base_header.param.iter() {
+                let sub_params = p.param.as_deref().into_iter().flatten().map(|sp| (&sp.name, &sp.bits, &sp.defines));
+                self.validate_param(&element, &p.name, &p.bits, sub_params)?;
+            }
+        }
+        for header in self.header.iter() {
+            let element = format!("Header {}", header.name);
+            check_number(&element, "Key", &header.key)?;
+            if let Some(key) = &header.base_header_key
+                && !self.base_header.iter().any(|base_header| base_header.key == *key)
+            {
+                return Err(invalid(element, format!("no BaseHeader with Key {:?}", key)));
+            }
+            for p in header.param.iter() {
+                let sub_params = p.param.as_deref().into_iter().flatten().map(|sp| (&sp.name, &sp.bits, &sp.defines));
+                self.validate_param(&element, &p.name, &p.bits, sub_params)?;
+            }
+            for validation in header.validation.as_deref().into_iter().flatten() {
+                check_hex(&format!("{} / Validation {}", element, validation.param_name), "ParamHexValue", &validation.param_hex_value)?;
+            }
+        }
+        for define_set in self.define_set.iter() {
+            for define in define_set.define.iter() {
+                check_hex(&format!("DefineSet {} / Define {}", define_set.name, define.name), "Key", &define.key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a header parameter of whole bytes and its sub-parameters, which
+    /// must fit in the parameter and in a u32.
+    fn validate_param<'a>(
+        &self,
+        element: &str,
+        name: &str,
+        bits: &str,
+        sub_params: impl Iterator<Item = (&'a Str, &'a Str, &'a Option<Str>)>,
+    ) -> Result<(), InvalidElement> {
+        let element = format!("{} / Param {}", element, name);
+        let bits = match bits.parse::<usize>() {
+            Ok(bits) if bits > 0 && bits % 8 == 0 => bits,
+            _ => return Err(invalid(element, format!("Bits {:?} is not a number of bytes", bits))),
+        };
+        // Sub-parameters are read from a u32 with shifts and masks.
+        let mut total = 0;
+        for (name, sub_bits, defines) in sub_params {
+            let element = format!("{} / Param {}", element, name);
+            match sub_bits.parse::<usize>() {
+                Ok(sub_bits) if (1..32).contains(&sub_bits) => total += sub_bits,
+                _ => return Err(invalid(element, format!("Bits {:?} is not a number from 1 to 31", sub_bits))),
+            }
+            if let Some(defines) = defines
+                && !self.define_set.iter().any(|define_set| define_set.name == *defines)
+            {
+                return Err(invalid(element, format!("no DefineSet named {:?}", defines)));
+            }
+        }
+        if total > bits.min(32) {
+            return Err(invalid(element, format!("sub-parameters have {} bits, more than the {} of the parameter", total, bits.min(32))));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(element: String, message: String) -> InvalidElement {
+    InvalidElement { element, message }
+}
+
+/// Check that the attribute `name` of `element` is a hex number, with or
+/// without 0x.
+fn check_hex(element: &str, name: &str, value: &str) -> Result<(), InvalidElement> {
+    match u32::from_str_radix(value.trim_start_matches("0x"), 16) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(invalid(element.to_string(), format!("{} {:?} is not a hex number", name, value))),
+    }
+}
+
+/// Parse FrameDefinition.xml, see `xml::parse()`.
+pub fn parse(xml_data: &str) -> Result<FrameDefinition, serde_path_to_error::Error<quick_xml::DeError>> {
+    let xd = &mut quick_xml::de::Deserializer::from_str(xml_data);
+    serde_path_to_error::deserialize(xd)
+}
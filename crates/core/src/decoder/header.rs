@@ -0,0 +1,183 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use crate::mac::Layout;
+use super::command::{
+    DecodedParam,
+    ParamValue,
+};
+use super::frame_definition::FrameDefinition;
+
+/// The base header and header of an MPDU.
+pub(super) struct DecodedHeader {
+    pub base_header: String,
+    pub header: Option<String>,
+    pub fields: Vec<DecodedParam>,
+    /// Position of the payload.
+    pub end: usize,
+}
+
+/// A header parameter or, with a dotted name, e.g., "Properties1.HeaderType",
+/// one of its sub-parameters.
+#[derive(Debug)]
+struct Field {
+    name: String,
+    offset: usize,
+    bits: usize,
+    value: u32,
+    /// Name of the DefineSet naming the values, if any.
+    defines: Option<String>,
+}
+
+/// Name, bits, sub-parameters (name, bits, define set), OptRef and SizeRef
+/// of a header parameter.
+type ParamDef<'a> = (&'a str, &'a str, Vec<(&'a str, &'a str, Option<&'a str>)>, Option<&'a str>, Option<&'a str>);
+
+/// Decode the base header of `layout` and the first header of the base
+/// header that passes its validations, e.g., BROADCAST before SINGLECAST.
+pub(super) fn decode(fd: &FrameDefinition, mpdu: &[u8], layout: Layout) -> DecodedHeader {
+    let key = match layout {
+        Layout::TwoChannel => "0",
+        Layout::ThreeChannel => "1",
+        Layout::LongRange => "2",
+    };
+    let mut decoded = DecodedHeader {
+        base_header: String::new(),
+        header: None,
+        fields: Vec::new(),
+        end: mpdu.len(),
+    };
+    let Some(base_header) = fd.base_header.iter().find(|base_header| base_header.key == key) else {
+        return decoded;
+    };
+    decoded.base_header = base_header.name.to_string();
+
+    let mut offset = 0;
+    let params = base_header.param.iter().map(|p| {
+        let sub_params = p.param.as_deref().into_iter().flatten().map(|sp| (sp.name.as_ref(), sp.bits.as_ref(), sp.defines.as_deref())).collect();
+        (p.name.as_ref(), p.bits.as_ref(), sub_params, None, None)
+    });
+    let Some(fields) = read_fields(mpdu, &mut offset, params, &[]) else {
+        // Too short for the base header.
+        return decoded;
+    };
+
+    let mut header_fields = Vec::new();
+    for header in fd.header.iter() {
+        if header.base_header_key.as_deref() != Some(key) {
+            continue;
+        }
+        let mut header_offset = offset;
+        let params = header.param.iter().map(|p| {
+            let sub_params = p.param.as_deref().into_iter().flatten().map(|sp| (sp.name.as_ref(), sp.bits.as_ref(), sp.defines.as_deref())).collect();
+            (p.name.as_ref(), p.bits.as_ref(), sub_params, p.opt_ref.as_deref(), p.size_ref.as_deref())
+        });
+        let Some(candidate) = read_fields(mpdu, &mut header_offset, params, &fields) else {
+            continue;
+        };
+        let valid = header.validation.as_deref().into_iter().flatten().all(|validation| {
+            let expected = u32::from_str_radix(validation.param_hex_value.trim_start_matches("0x"), 16).ok();
+            let actual = value_of(&candidate, &validation.param_name).or_else(|| value_of(&fields, &validation.param_name));
+            actual.is_none() || actual == expected
+        });
+        if valid {
+            decoded.header = Some(header.name.to_string());
+            header_fields = candidate;
+            offset = header_offset;
+            break;
+        }
+    }
+
+    decoded.fields = to_params(fd, fields.into_iter().chain(header_fields));
+    decoded.end = offset;
+    decoded
+}
+
+fn value_of(fields: &[Field], name: &str) -> Option<u32> {
+    fields.iter().find(|field| field.name == name).map(|field| field.value)
+}
+
+/// Name of `value` in the DefineSet `set`.
+fn define_name<'a>(fd: &'a FrameDefinition, set: &str, value: u32) -> Option<&'a str> {
+    let define_set = fd.define_set.iter().find(|ds| ds.name == set)?;
+    define_set
+        .define
+        .iter()
+        .find(|define| u32::from_str_radix(define.key.trim_start_matches("0x"), 16).ok() == Some(value))
+        .map(|define| define.name.as_ref())
+}
+
+/// Nest the sub-parameters under their parameters.
+fn to_params(fd: &FrameDefinition, fields: impl Iterator<Item = Field>) -> Vec<DecodedParam> {
+    let mut params: Vec<DecodedParam> = Vec::new();
+    for field in fields {
+        let value = match &field.defines {
+            Some(set) => ParamValue::Enum {
+                value: field.value,
+                name: define_name(fd, set, field.value).map(str::to_string),
+            },
+            None => ParamValue::Number(field.value),
+        };
+        match (field.name.split_once('.'), params.last_mut()) {
+            (Some((_, name)), Some(parent)) => parent.children.push(DecodedParam {
+                name: name.to_string(),
+                offset: field.offset,
+                len: 0,
+                value,
+                children: Vec::new(),
+            }),
+            _ => params.push(DecodedParam {
+                name: field.name,
+                offset: field.offset,
+                len: field.bits / 8,
+                value,
+                children: Vec::new(),
+            }),
+        }
+    }
+    params
+}
+
+/// Read big-endian parameters starting at `offset`. Sub-parameters are
+/// listed from the least significant bit. Optional parameters are skipped
+/// if the `OptRef` field of `known` is 0, and `SizeRef` repeats a parameter.
+/// Returns None if the frame is too short.
+fn read_fields<'a>(
+    frame: &[u8],
+    offset: &mut usize,
+    params: impl Iterator<Item = ParamDef<'a>>,
+    known: &[Field],
+) -> Option<Vec<Field>> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (name, bits, sub_params, opt_ref, size_ref) in params {
+        let lookup = |fields: &[Field], name: &str| value_of(fields, name).or_else(|| value_of(known, name));
+        if opt_ref.is_some_and(|r| lookup(&fields, r) == Some(0)) {
+            continue;
+        }
+        let bits: usize = bits.parse().ok()?;
+        let count = match size_ref {
+            Some(r) => lookup(&fields, r).unwrap_or(0) as usize,
+            None => 1,
+        };
+        for _ in 0..count {
+            let bytes = frame.get(*offset..*offset + bits / 8)?;
+            let value = bytes.iter().take(4).fold(0u32, |value, byte| value << 8 | *byte as u32);
+            let field_offset = *offset;
+            *offset += bits / 8;
+            fields.push(Field { name: name.to_string(), offset: field_offset, bits, value, defines: None });
+
+            let mut bit_offset = 0;
+            for (sub_name, sub_bits, defines) in &sub_params {
+                let n: usize = sub_bits.parse().ok()?;
+                fields.push(Field {
+                    name: format!("{}.{}", name, sub_name),
+                    offset: field_offset,
+                    bits: n,
+                    value: value >> bit_offset & ((1 << n) - 1),
+                    defines: defines.map(str::to_string),
+                });
+                bit_offset += n;
+            }
+        }
+    }
+    Some(fields)
+}
@@ -0,0 +1,454 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Decoder of Z-Wave frames, driven by FrameDefinition.xml for the MAC
+//! headers and zwave.xml for the command classes. build.rs turns both into
+//! the static `tables`, so nothing is parsed at run time. `Definitions` merges
+//! files of the same format on top, e.g., with newer command classes or a
+//! proprietary one.
+//!
+//! `decode()` returns a `DecodedFrame`: the fields of the headers and the
+//! parameters of the command, with their values, the names of enumerated
+//! values and their positions in the MPDU. It serializes, e.g., to JSON, and
+//! prints as indented text.
+//!
+//! zwave.xml defines each version of a command class separately. Commands
+//! decode with the highest version, unless a `Decoder` was given a version
+//! or learned the version of the sending node, or else of the destination
+//! node, from a Version Command Class Report.
+//!
+//! ```
+//! use zniff_rs_core::decoder;
+//! use zniff_rs_core::mac::Layout;
+//!
+//! // Basic Set from node 1 to node 2.
+//! let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x01, 0x0D, 0x02, 0x20, 0x01, 0xFF, 0x92];
+//! let decoded = decoder::decode(&mpdu, Layout::TwoChannel);
+//! assert_eq!(decoded.header.as_deref(), Some("SINGLECAST"));
+//! assert_eq!(decoded.command.unwrap().command.as_deref(), Some("Basic Set"));
+//! ```
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use thiserror::Error;
+use crate::mac::{
+    self,
+    ExploreCommand,
+    Layout,
+    MacHeader,
+};
+use crate::types::Frame;
+
+mod command;
+pub use command::{
+    DecodedCommand,
+    DecodedParam,
+    ParamValue,
+};
+use command::CommandDecoder;
+
+pub mod frame_definition;
+use frame_definition::FrameDefinition;
+
+mod header;
+
+pub mod tables;
+
+pub mod xml;
+use xml::{
+    InvalidElement,
+    ZwClasses,
+};
+
+/// Command class and command of a Version Command Class Report.
+const COMMAND_CLASS_VERSION: u8 = 0x86;
+const VERSION_COMMAND_CLASS_REPORT: u8 = 0x14;
+
+/// Errors of loading definitions, with the path of the file.
+#[derive(Debug, Error)]
+pub enum DefinitionError {
+    #[error("{0}: {1}")]
+    Io(String, #[source] io::Error),
+    /// The XML does not match the structs, e.g., a missing attribute.
+    #[error("{0}: {1}")]
+    Xml(String, #[source] serde_path_to_error::Error<quick_xml::DeError>),
+    /// The XML has values the decoder cannot use.
+    #[error("{0}: {1}")]
+    Invalid(String, #[source] InvalidElement),
+}
+
+/// The command classes and frame headers known to the decoder.
+#[derive(Debug, Clone)]
+pub struct Definitions {
+    classes: Cow<'static, ZwClasses>,
+    frames: Cow<'static, FrameDefinition>,
+}
+
+static BUILT_IN: Definitions = Definitions {
+    classes: Cow::Borrowed(&tables::CLASSES),
+    frames: Cow::Borrowed(&tables::FRAMES),
+};
+
+impl Default for Definitions {
+    /// The built-in definitions.
+    fn default() -> Self {
+        BUILT_IN.clone()
+    }
+}
+
+impl Definitions {
+    /// The built-in definitions with a zwave.xml and a FrameDefinition.xml
+    /// file merged on top, e.g., those of `--cc-xml` and `--frame-def`.
+    pub fn load(cc_xml: Option<&Path>, frame_def: Option<&Path>) -> Result<Self, DefinitionError> {
+        let mut definitions = Definitions::default();
+        if let Some(path) = cc_xml {
+            definitions.merge_cc_xml(path)?;
+        }
+        if let Some(path) = frame_def {
+            definitions.merge_frame_def(path)?;
+        }
+        Ok(definitions)
+    }
+
+    pub fn classes(&self) -> &ZwClasses {
+        &self.classes
+    }
+
+    pub fn frames(&self) -> &FrameDefinition {
+        &self.frames
+    }
+
+    /// Merge a file in the format of zwave.xml, see `ZwClasses::merge()`.
+    /// Leaves the definitions unchanged on errors.
+    pub fn merge_cc_xml(&mut self, path: impl AsRef<Path>) -> Result<(), DefinitionError> {
+        let (path, xml) = read(path.as_ref())?;
+        let other = xml::parse(&xml).map_err(|e| DefinitionError::Xml(path.clone(), e))?;
+        let mut classes = self.classes.as_ref().clone();
+        classes.merge(&other);
+        classes.validate().map_err(|e| DefinitionError::Invalid(path, e))?;
+        self.classes = Cow::Owned(classes);
+        Ok(())
+    }
+
+    /// Merge a file in the format of FrameDefinition.xml, see
+    /// `FrameDefinition::merge()`. Leaves the definitions unchanged on
+    /// errors.
+    pub fn merge_frame_def(&mut self, path: impl AsRef<Path>) -> Result<(), DefinitionError> {
+        let (path, xml) = read(path.as_ref())?;
+        let other = frame_definition::parse(&xml).map_err(|e| DefinitionError::Xml(path.clone(), e))?;
+        let mut frames = self.frames.as_ref().clone();
+        frames.merge(&other);
+        frames.validate().map_err(|e| DefinitionError::Invalid(path, e))?;
+        self.frames = Cow::Owned(frames);
+        Ok(())
+    }
+}
+
+/// The path for errors and the contents of a file.
+fn read(path: &Path) -> Result<(String, String), DefinitionError> {
+    let name = path.display().to_string();
+    match fs::read_to_string(path) {
+        Ok(xml) => Ok((name, xml)),
+        Err(e) => Err(DefinitionError::Io(name, e)),
+    }
+}
+
+/// A decoded MPDU.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedFrame {
+    /// Name of the base header, e.g., "BASIC".
+    pub base_header: String,
+    /// Name of the header, e.g., "SINGLECAST", None if no header matches.
+    pub header: Option<String>,
+    /// Fields of the base header and the header. Empty if the MPDU is too
+    /// short for the base header.
+    pub fields: Vec<DecodedParam>,
+    /// The MAC header, e.g., for the route of a routed frame. None if it
+    /// cannot be decoded.
+    pub mac: Option<MacHeader>,
+    /// The payload after the headers, without the checksum.
+    pub payload: Vec<u8>,
+    /// None for frames without payload and unknown command classes.
+    pub command: Option<DecodedCommand>,
+}
+
+/// Decodes the frames of a capture, learning the command class versions of
+/// nodes from their Version Command Class Reports.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    definitions: &'a Definitions,
+    /// Command class versions by node ID and command class.
+    versions: HashMap<(u16, u8), u8>,
+    version: Option<u8>,
+}
+
+impl Default for Decoder<'_> {
+    fn default() -> Self {
+        Decoder::with_definitions(&BUILT_IN)
+    }
+}
+
+impl<'a> Decoder<'a> {
+    /// A decoder with the built-in definitions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_definitions(definitions: &'a Definitions) -> Self {
+        Decoder {
+            definitions,
+            versions: HashMap::new(),
+            version: None,
+        }
+    }
+
+    /// Decode all commands with this command class version instead of the
+    /// versions of the nodes.
+    pub fn cc_version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// The version of a command class supported by a node, if known.
+    pub fn version(&self, node_id: u16, class: u8) -> Option<u8> {
+        self.versions.get(&(node_id, class)).copied()
+    }
+
+    /// Decode commands of a command class sent by or to a node with the
+    /// definitions of `version`.
+    pub fn set_version(&mut self, node_id: u16, class: u8, version: u8) {
+        self.versions.insert((node_id, class), version);
+    }
+
+    /// Decode an MPDU with the base header of `layout`.
+    ///
+    /// The speed of the frame is unknown, so a checksum at the end is only
+    /// recognized if it verifies.
+    pub fn decode(&mut self, mpdu: &[u8], layout: Layout) -> DecodedFrame {
+        let checksum_len = if layout == Layout::TwoChannel && mac::checksum_ok(mpdu, 0) {
+            1
+        } else if mac::checksum_ok(mpdu, 2) {
+            2
+        } else {
+            0
+        };
+        self.decode_mpdu(mpdu, layout, checksum_len)
+    }
+
+    /// Decode a data frame with the base header and checksum of its region
+    /// and speed.
+    pub fn decode_frame(&mut self, frame: &Frame) -> DecodedFrame {
+        let checksum_len = if frame.speed <= 1 { 1 } else { 2 };
+        self.decode_mpdu(&frame.payload, Layout::for_frame(frame.region, frame.speed), checksum_len)
+    }
+
+    fn decode_mpdu(&mut self, mpdu: &[u8], layout: Layout, checksum_len: usize) -> DecodedFrame {
+        let header = header::decode(&self.definitions.frames, mpdu, layout);
+        let mac = MacHeader::decode(mpdu, layout).ok();
+        let payload = &mpdu[header.end..mpdu.len().saturating_sub(checksum_len).max(header.end)];
+
+        let node_id = mac.as_ref().map_or(0, |mac| mac.src_node_id);
+        if let [COMMAND_CLASS_VERSION, VERSION_COMMAND_CLASS_REPORT, class, version, ..] = *payload
+            && version > 0
+        {
+            self.set_version(node_id, class, version);
+        }
+        let commands = CommandDecoder {
+            classes: &self.definitions.classes,
+            versions: &self.versions,
+            version: self.version,
+            node_id,
+            dst_node_id: mac.as_ref().and_then(|mac| mac.dst_node_id),
+        };
+        let command = commands.decode(payload, header.end);
+
+        DecodedFrame {
+            base_header: header.base_header,
+            header: header.header,
+            fields: header.fields,
+            mac,
+            payload: payload.to_vec(),
+            command,
+        }
+    }
+}
+
+/// Decode an MPDU with the highest command class versions, see
+/// `Decoder::decode()`.
+pub fn decode(mpdu: &[u8], layout: Layout) -> DecodedFrame {
+    Decoder::new().decode(mpdu, layout)
+}
+
+impl Frame {
+    /// Decode a data frame with the highest command class versions, see
+    /// `Decoder::decode_frame()`.
+    pub fn decode(&self) -> DecodedFrame {
+        Decoder::new().decode_frame(self)
+    }
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fields.is_empty() {
+            return writeln!(f, "Frame too short for the {} header", self.base_header);
+        }
+        match &self.header {
+            Some(header) => writeln!(f, "Header: {} {}", self.base_header, header)?,
+            None => writeln!(f, "Header: {}", self.base_header)?,
+        }
+        for field in &self.fields {
+            field.fmt_indented(f, 2)?;
+        }
+
+        if let Some(route) = self.mac.as_ref().and_then(|mac| mac.route.as_ref()) {
+            writeln!(f, "Route: {:?}, hop {}, repeaters {:?}", route.direction, route.hop, route.repeaters)?;
+        }
+        if let Some(node_ids) = self.mac.as_ref().and_then(|mac| mac.multicast.as_ref()) {
+            writeln!(f, "Destinations: {:?}", node_ids)?;
+        }
+        if let Some(explorer) = self.mac.as_ref().and_then(|mac| mac.explorer.as_ref()) {
+            writeln!(f, "Explorer: TTL {}, repeaters {:?}", explorer.ttl, explorer.repeaters)?;
+            if let ExploreCommand::SearchResult { repeaters, .. } = &explorer.command {
+                writeln!(f, "Search result repeaters: {:?}", repeaters)?;
+            }
+        }
+
+        match &self.command {
+            Some(command) => write!(f, "{}", command),
+            None => match self.payload.first() {
+                Some(key) => writeln!(f, "Unknown command class 0x{:02X}", key),
+                // E.g., acknowledgements and routed errors have no payload.
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x01, 0x0D, 0x02, 0x20, 0x01, 0xFF, 0x92];
+        let decoded = decode(&mpdu, Layout::TwoChannel);
+        assert_eq!(decoded.base_header, "BASIC");
+        assert_eq!(decoded.header.as_deref(), Some("SINGLECAST"));
+        assert_eq!(decoded.mac.as_ref().unwrap().src_node_id, 1);
+        assert_eq!(decoded.payload, [0x20, 0x01, 0xFF]);
+
+        let properties = decoded.fields.iter().find(|field| field.name == "Properties1").unwrap();
+        assert_eq!((properties.offset, properties.len), (5, 1));
+        assert_eq!(properties.children[0].name, "HeaderType");
+        assert_eq!(properties.children[0].value, ParamValue::Enum { value: 1, name: Some("Singlecast".to_string()) });
+
+        let command = decoded.command.unwrap();
+        assert_eq!(command.offset, 9);
+        assert_eq!(command.command.as_deref(), Some("Basic Set"));
+        assert_eq!(command.params[0].name, "Value");
+        assert_eq!(command.params[0].offset, 11);
+        assert_eq!(command.params[0].value, ParamValue::Number(0xFF));
+
+        // Too short for the base header.
+        let decoded = decode(&mpdu[..6], Layout::TwoChannel);
+        assert!(decoded.fields.is_empty());
+        assert_eq!(decoded.command, None);
+    }
+
+    #[test]
+    fn test_versions() {
+        // Node 11 reports version 1 of the Basic Command Class.
+        let report = [0xE2, 0xEA, 0x36, 0xC3, 0x0B, 0x41, 0x01, 0x0E, 0x01, 0x86, 0x14, 0x20, 0x01];
+        let mut decoder = Decoder::new();
+        decoder.decode(&report, Layout::TwoChannel);
+        assert_eq!(decoder.version(11, 0x20), Some(1));
+
+        let basic_report = |src_node_id| [0xE2, 0xEA, 0x36, 0xC3, src_node_id, 0x41, 0x02, 0x0C, 0x01, 0x20, 0x03, 0xFF];
+        let command = decoder.decode(&basic_report(11), Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.version, 1);
+        assert_eq!(command.params.len(), 1);
+        let command = decoder.decode(&basic_report(12), Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.version, 2);
+
+        // Basic Set from the controller to node 11 and to node 12.
+        let basic_set = |dst_node_id| [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x03, 0x0C, dst_node_id, 0x20, 0x01, 0xFF];
+        let command = decoder.decode(&basic_set(11), Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.version, 1);
+        let command = decoder.decode(&basic_set(12), Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.version, 2);
+
+        let mut decoder = Decoder::new().cc_version(1);
+        let command = decoder.decode(&basic_report(12), Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.version, 1);
+    }
+
+    /// Write `xml` to a file of the temporary directory.
+    fn temp_file(name: &str, xml: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("zniff-rs-{}-{}", std::process::id(), name));
+        fs::write(&path, xml).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_definitions() {
+        let acme = r#"<zw_classes>
+            <cmd_class key="0xEE" version="1" name="COMMAND_CLASS_ACME" help="Command Class Acme">
+                <cmd key="0x01" name="ACME_SET" help="Acme Set">
+                    <param key="0x00" name="Level" type="BYTE"/>
+                </cmd>
+            </cmd_class>
+        </zw_classes>"#;
+        let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x01, 0x0C, 0x02, 0xEE, 0x01, 0x42];
+        assert_eq!(decode(&mpdu, Layout::TwoChannel).command, None);
+
+        let mut definitions = Definitions::load(Some(&temp_file("acme.xml", acme)), None).unwrap();
+        let command = Decoder::with_definitions(&definitions).decode(&mpdu, Layout::TwoChannel).command.unwrap();
+        assert_eq!(command.command.as_deref(), Some("Acme Set"));
+        assert_eq!(command.params[0].value, ParamValue::Number(0x42));
+        // The built-in classes are still there.
+        assert_eq!(definitions.classes().cmd_class.len(), tables::CLASSES.cmd_class.len() + 1);
+
+        // Errors name the element and leave the definitions unchanged.
+        let path = temp_file("invalid.xml", &acme.replace("BYTE", "BYTES"));
+        let e = definitions.merge_cc_xml(&path).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!("{}: cmd_class COMMAND_CLASS_ACME v1 / cmd ACME_SET / param Level: unknown type \"BYTES\"", path.display()),
+        );
+        let path = temp_file("missing.xml", &acme.replace(r#" name="ACME_SET""#, ""));
+        let e = definitions.merge_cc_xml(&path).unwrap_err();
+        assert_eq!(e.to_string(), format!("{}: cmd_class[0].cmd[0]: missing field `@name`", path.display()));
+        let frame_def = r#"<FrameDefinition><Header Key="99" Name="ACME" Text="Acme" BaseHeaderKey="9"
+            IsAck="false" IsError="false" IsMulticast="false" IsRouted="false">
+            <Param Order="0" Name="Acme" Text="Acme" Type="BYTE" Bits="8"/></Header></FrameDefinition>"#;
+        let path = temp_file("frames.xml", frame_def);
+        let e = definitions.merge_frame_def(&path).unwrap_err();
+        assert_eq!(e.to_string(), format!("{}: Header ACME: no BaseHeader with Key \"9\"", path.display()));
+        let sub_params = |bits: [&str; 2]| {
+            frame_def.replace(r#"BaseHeaderKey="9""#, r#"BaseHeaderKey="0""#).replace(
+                r#"Bits="8"/>"#,
+                &format!(
+                    r#"Bits="32"><Param Order="0" Name="Low" Text="Low" Type="HEX" Bits="{}"/>
+                    <Param Order="1" Name="High" Text="High" Type="HEX" Bits="{}"/></Param>"#,
+                    bits[0], bits[1],
+                ),
+            )
+        };
+        for (bits, message) in [
+            (["0x8", "8"], "Param Acme / Param Low: Bits \"0x8\" is not a number from 1 to 31"),
+            (["8", "32"], "Param Acme / Param High: Bits \"32\" is not a number from 1 to 31"),
+            (["16", "17"], "Param Acme: sub-parameters have 33 bits, more than the 32 of the parameter"),
+        ] {
+            let path = temp_file("frames.xml", &sub_params(bits));
+            let e = definitions.merge_frame_def(&path).unwrap_err();
+            assert_eq!(e.to_string(), format!("{}: Header ACME / {}", path.display(), message));
+        }
+        assert_eq!(definitions.frames(), &tables::FRAMES);
+        definitions.merge_frame_def(temp_file("frames.xml", &sub_params(["16", "16"]))).unwrap();
+        assert_eq!(definitions.frames().header.len(), tables::FRAMES.header.len() + 1);
+        assert_eq!(definitions.classes().cmd_class.len(), tables::CLASSES.cmd_class.len() + 1);
+    }
+}
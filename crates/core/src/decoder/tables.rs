@@ -0,0 +1,24 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! `CLASSES` from zwave.xml and `FRAMES` from FrameDefinition.xml, generated
+//! by build.rs.
+use std::borrow::Cow;
+use super::frame_definition::*;
+use super::xml::*;
+
+include!(concat!(env!("OUT_DIR"), "/tables.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{
+        frame_definition,
+        xml,
+    };
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(xml::parse(include_str!("zwave.xml")).unwrap(), CLASSES);
+        assert_eq!(frame_definition::parse(include_str!("FrameDefinition.xml")).unwrap(), FRAMES);
+    }
+}
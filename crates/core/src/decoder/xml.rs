@@ -0,0 +1,458 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use std::borrow::Cow;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Text of an attribute or element, borrowed by the tables generated at
+/// build time, owned when parsed at run time.
+pub type Str = Cow<'static, str>;
+
+/// Repeated elements, see `Str`.
+pub type List<T> = Cow<'static, [T]>;
+
+/*
+ * The following structs are initialliy generated from zwave.xml via
+ * https://thomblin.github.io/xml_schema_generator/.
+ *
+ * Some modifications are made to support cases where elements are not consecutively present.
+ * For instance: <param/><variant_group/><param/>.
+ */
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ZwClasses {
+    #[serde(rename = "@version", default)]
+    pub version: Str,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    /// Files merged on top of the built-in definitions may leave out any of
+    /// the lists.
+    #[serde(default)]
+    pub bas_dev: List<BasDev>,
+    #[serde(default)]
+    pub gen_dev: List<GenDev>,
+    #[serde(default)]
+    pub cmd_class: List<CmdClass>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BasDev {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@help")]
+    pub help: Str,
+    #[serde(rename = "@comment")]
+    pub comment: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GenDev {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@help")]
+    pub help: Str,
+    #[serde(rename = "@comment")]
+    pub comment: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    pub spec_dev: List<SpecDev>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpecDev {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@help")]
+    pub help: Str,
+    #[serde(rename = "@comment")]
+    pub comment: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdClass {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@version")]
+    pub version: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@help")]
+    pub help: Str,
+    #[serde(rename = "@comment")]
+    pub comment: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    pub cmd: Option<List<Cmd>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cmd {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@help")]
+    pub help: Str,
+    #[serde(rename = "@support_mode")]
+    pub support_mode: Option<Str>,
+    #[serde(rename = "@comment")]
+    pub comment: Option<Str>,
+    #[serde(rename = "@cmd_mask")]
+    pub cmd_mask: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(rename = "$value", default)]
+    pub children: Option<List<CmdClassCmdChild>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CmdClassCmdChild {
+    Param(CmdClassCmdParam),
+    VariantGroup(CmdVariantGroup),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdClassCmdParam {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@type")]
+    pub param_type: Str,
+    #[serde(rename = "@encaptype")]
+    pub encaptype: Option<Str>,
+    #[serde(rename = "@comment")]
+    pub comment: Option<Str>,
+    #[serde(rename = "@optionalmask")]
+    pub optionalmask: Option<Str>,
+    #[serde(rename = "@optionaloffs")]
+    pub optionaloffs: Option<Str>,
+    #[serde(rename = "@cmd_mask")]
+    pub cmd_mask: Option<Str>,
+    #[serde(rename = "@skipfield")]
+    pub skipfield: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    /// Bitflags, bitfields and fieldenums of a STRUCT_BYTE are interleaved,
+    /// so all children are kept in document order.
+    #[serde(rename = "$value", default)]
+    pub children: List<CmdParamChild>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CmdParamChild {
+    Variant(CmdParamVariant),
+    Bitflag(CmdParamBitflag),
+    Bitfield(CmdParamBitfield),
+    Fieldenum(CmdParamFieldenum),
+    Bitmask(CmdParamBitmask),
+    Const(CmdParamConst),
+    Arrayattrib(Arrayattrib),
+    MultiArray(MultiArray),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamVariant {
+    #[serde(rename = "@paramoffs")]
+    pub paramoffs: Str,
+    #[serde(rename = "@sizemask")]
+    pub sizemask: Str,
+    #[serde(rename = "@is_ascii")]
+    pub is_ascii: Option<Str>,
+    #[serde(rename = "@sizechange")]
+    pub sizechange: Option<Str>,
+    #[serde(rename = "@sizeoffs")]
+    pub sizeoffs: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamBitflag {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@flagname")]
+    pub flagname: Str,
+    #[serde(rename = "@flagmask")]
+    pub flagmask: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamBitfield {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@fieldname")]
+    pub fieldname: Str,
+    #[serde(rename = "@fieldmask")]
+    pub fieldmask: Str,
+    #[serde(rename = "@shifter")]
+    pub shifter: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamFieldenum {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@fieldname")]
+    pub fieldname: Str,
+    #[serde(rename = "@fieldmask")]
+    pub fieldmask: Str,
+    #[serde(rename = "@shifter")]
+    pub shifter: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    #[serde(default)]
+    pub fieldenum: List<CmdParamFieldenumValue>,
+}
+
+/// A value of a fieldenum, numbered from 0 unless it has a key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamFieldenumValue {
+    #[serde(rename = "@key")]
+    pub key: Option<Str>,
+    #[serde(rename = "@value")]
+    pub value: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamBitmask {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@paramoffs")]
+    pub paramoffs: Str,
+    #[serde(rename = "@lenmask")]
+    pub lenmask: Str,
+    #[serde(rename = "@lenoffs")]
+    pub lenoffs: Option<Str>,
+    #[serde(rename = "@len")]
+    pub len: Option<Str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdParamConst {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@flagname")]
+    pub flagname: Str,
+    #[serde(rename = "@flagmask")]
+    pub flagmask: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Arrayattrib {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@len")]
+    pub len: Str,
+    #[serde(rename = "@is_ascii")]
+    pub is_ascii: Option<Str>,
+}
+
+/// Either the location of the parameter selecting a table of a MULTI_ARRAY,
+/// or one of its tables.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MultiArray {
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    pub paramdescloc: Option<Paramdescloc>,
+    pub bitflag: Option<List<CmdParamBitflag>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Paramdescloc {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@param")]
+    pub param: Str,
+    #[serde(rename = "@paramdesc")]
+    pub paramdesc: Str,
+    #[serde(rename = "@paramstart")]
+    pub paramstart: Str,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CmdVariantGroup {
+    #[serde(rename = "@key")]
+    pub key: Str,
+    #[serde(rename = "@name")]
+    pub name: Str,
+    #[serde(rename = "@paramOffs")]
+    pub param_offs: Str,
+    #[serde(rename = "@sizemask")]
+    pub sizemask: Str,
+    #[serde(rename = "@sizeoffs")]
+    pub sizeoffs: Str,
+    #[serde(rename = "@optionalmask")]
+    pub optionalmask: Option<Str>,
+    #[serde(rename = "@optionaloffs")]
+    pub optionaloffs: Option<Str>,
+    #[serde(rename = "@moretofollowmask")]
+    pub moretofollowmask: Option<Str>,
+    #[serde(rename = "@moretofollowoffs")]
+    pub moretofollowoffs: Option<Str>,
+    #[serde(rename = "$text")]
+    pub text: Option<Str>,
+    /// Parameters and nested variant groups, in document order.
+    #[serde(rename = "$value", default)]
+    pub children: List<CmdClassCmdChild>,
+}
+
+/// Types of parameters, see `CmdClassCmdParam::param_type`.
+const PARAM_TYPES: &[&str] = &[
+    "BYTE", "WORD", "BIT_24", "DWORD", "STRUCT_BYTE", "CONST", "MARKER", "VARIANT", "ARRAY", "BITMASK", "MULTI_ARRAY",
+];
+
+const ENCAPTYPES: &[&str] = &[
+    "NODE_NUMBER", "CMD_CLASS_REF", "CMD_REF", "CMD_DATA", "CMD_ENCAP", "BAS_DEV_REF", "GEN_DEV_REF", "SPEC_DEV_REF",
+];
+
+/// An element with a value the decoder cannot use, e.g., a key that is not a
+/// number.
+#[derive(Debug, Error)]
+#[error("{element}: {message}")]
+pub struct InvalidElement {
+    /// Path of the element, e.g., "cmd_class COMMAND_CLASS_BASIC v1 / cmd
+    /// BASIC_SET / param Value".
+    pub element: String,
+    pub message: String,
+}
+
+/// Parse a key, mask or offset, written either as hex with 0x or as decimal.
+pub fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Check that the attribute `name` of `element` is a number.
+pub(crate) fn check_number(element: &str, name: &str, value: &str) -> Result<(), InvalidElement> {
+    match parse_number(value) {
+        Some(_) => Ok(()),
+        None => Err(InvalidElement {
+            element: element.to_string(),
+            message: format!("{} {:?} is not a number", name, value),
+        }),
+    }
+}
+
+/// Replace the items of `list` that are the `same` as one of `items` and
+/// append the others.
+pub(crate) fn merge_list<T: Clone>(list: &mut List<T>, items: &[T], same: impl Fn(&T, &T) -> bool) {
+    let list = list.to_mut();
+    for item in items {
+        match list.iter_mut().find(|existing| same(existing, item)) {
+            Some(existing) => *existing = item.clone(),
+            None => list.push(item.clone()),
+        }
+    }
+}
+
+impl ZwClasses {
+    /// Merge the definitions of `other` on top: command classes replace
+    /// those with the same key and version, device classes those with the
+    /// same key, and the others are added.
+    pub fn merge(&mut self, other: &ZwClasses) {
+        merge_list(&mut self.bas_dev, &other.bas_dev, |a, b| parse_number(&a.key) == parse_number(&b.key));
+        merge_list(&mut self.gen_dev, &other.gen_dev, |a, b| parse_number(&a.key) == parse_number(&b.key));
+        merge_list(&mut self.cmd_class, &other.cmd_class, |a, b| {
+            parse_number(&a.key) == parse_number(&b.key) && parse_number(&a.version) == parse_number(&b.version)
+        });
+    }
+
+    /// Check the attributes the decoder relies on, e.g., that keys are
+    /// numbers and that parameters have a known type.
+    pub fn validate(&self) -> Result<(), InvalidElement> {
+        for dev in self.bas_dev.iter() {
+            check_number(&format!("bas_dev {}", dev.name), "key", &dev.key)?;
+        }
+        for dev in self.gen_dev.iter() {
+            let element = format!("gen_dev {}", dev.name);
+            check_number(&element, "key", &dev.key)?;
+            for spec_dev in dev.spec_dev.iter() {
+                check_number(&format!("{} / spec_dev {}", element, spec_dev.name), "key", &spec_dev.key)?;
+            }
+        }
+        for class in self.cmd_class.iter() {
+            let element = format!("cmd_class {} v{}", class.name, class.version);
+            check_number(&element, "key", &class.key)?;
+            check_number(&element, "version", &class.version)?;
+            for cmd in class.cmd.as_deref().into_iter().flatten() {
+                let element = format!("{} / cmd {}", element, cmd.name);
+                check_number(&element, "key", &cmd.key)?;
+                if let Some(mask) = &cmd.cmd_mask {
+                    check_number(&element, "cmd_mask", mask)?;
+                }
+                validate_children(&element, cmd.children.as_deref().into_iter().flatten())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the parameters and variant groups of `element`.
+fn validate_children<'a>(
+    element: &str,
+    children: impl Iterator<Item = &'a CmdClassCmdChild>,
+) -> Result<(), InvalidElement> {
+    for child in children {
+        match child {
+            CmdClassCmdChild::Param(p) => {
+                let element = format!("{} / param {}", element, p.name);
+                check_number(&element, "key", &p.key)?;
+                if !PARAM_TYPES.contains(&p.param_type.as_ref()) {
+                    return Err(InvalidElement { element, message: format!("unknown type {:?}", p.param_type) });
+                }
+                if let Some(encaptype) = &p.encaptype
+                    && !ENCAPTYPES.contains(&encaptype.as_ref())
+                {
+                    return Err(InvalidElement { element, message: format!("unknown encaptype {:?}", encaptype) });
+                }
+                for child in p.children.iter() {
+                    match child {
+                        CmdParamChild::Variant(v) => {
+                            check_number(&element, "paramoffs", &v.paramoffs)?;
+                            check_number(&element, "sizemask", &v.sizemask)?;
+                        },
+                        CmdParamChild::Bitflag(CmdParamBitflag { flagmask, .. })
+                        | CmdParamChild::Const(CmdParamConst { flagmask, .. }) => {
+                            check_number(&element, "flagmask", flagmask)?;
+                        },
+                        CmdParamChild::Bitfield(CmdParamBitfield { fieldmask, .. })
+                        | CmdParamChild::Fieldenum(CmdParamFieldenum { fieldmask, .. }) => {
+                            check_number(&element, "fieldmask", fieldmask)?;
+                        },
+                        CmdParamChild::Bitmask(b) => {
+                            check_number(&element, "paramoffs", &b.paramoffs)?;
+                            check_number(&element, "lenmask", &b.lenmask)?;
+                        },
+                        CmdParamChild::Arrayattrib(a) => check_number(&element, "len", &a.len)?,
+                        CmdParamChild::MultiArray(_) => {},
+                    }
+                }
+            },
+            CmdClassCmdChild::VariantGroup(g) => {
+                let element = format!("{} / variant_group {}", element, g.name);
+                check_number(&element, "key", &g.key)?;
+                check_number(&element, "paramOffs", &g.param_offs)?;
+                check_number(&element, "sizemask", &g.sizemask)?;
+                validate_children(&element, g.children.iter())?;
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Parse zwave.xml, e.g., to check it at build time. Errors name the path
+/// of the element that does not match the structs.
+pub fn parse(xml_data: &str) -> Result<ZwClasses, serde_path_to_error::Error<quick_xml::DeError>> {
+    let xd = &mut quick_xml::de::Deserializer::from_str(xml_data);
+    serde_path_to_error::deserialize(xd)
+}
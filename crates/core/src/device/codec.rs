@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use bytes::{
+    Buf,
+    BytesMut,
+};
+use tokio_util::codec::{
+    Decoder,
+    Encoder,
+};
+use crate::types::Frame;
+use crate::zniffer_command::{
+    Request,
+    ZnifferError,
+};
+use crate::zniffer_parser::{
+    Parser,
+    ParserResult,
+};
+
+/// A message sent by a Zniffer device.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Response to a request, see `zniffer_command::Response::parse()`.
+    Command {
+        id: u8,
+        payload: Vec<u8>,
+    },
+    Frame(Frame),
+}
+
+/// Splits the byte stream of a Zniffer device into messages and encodes
+/// requests.
+///
+/// Bytes following a complete message stay in the read buffer, so several
+/// frames received at once are all returned. Invalid frames are skipped.
+#[derive(Debug, Default)]
+pub struct ZnifferCodec {
+    parser: Parser,
+    invalid: usize,
+}
+
+impl ZnifferCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Discard a partially received message, e.g., after a pause in the
+    /// byte stream.
+    pub fn timeout(&mut self) {
+        self.parser.timeout();
+    }
+
+    /// Number of invalid frames skipped so far.
+    pub fn invalid_frames(&self) -> usize {
+        self.invalid
+    }
+}
+
+impl Decoder for ZnifferCodec {
+    type Item = Message;
+    type Error = ZnifferError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>, ZnifferError> {
+        while src.has_remaining() {
+            let value = src.get_u8();
+            match self.parser.parse(value) {
+                ParserResult::ValidCommand { id, payload } => {
+                    return Ok(Some(Message::Command { id, payload }));
+                },
+                ParserResult::ValidFrame { frame } => {
+                    return Ok(Some(Message::Frame(frame)));
+                },
+                ParserResult::IncompleteFrame => {
+                    // Continue parsing.
+                },
+                ParserResult::InvalidFrame => {
+                    self.invalid += 1;
+                },
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Encoder<Request> for ZnifferCodec {
+    type Error = ZnifferError;
+
+    fn encode(&mut self, request: Request, dst: &mut BytesMut) -> Result<(), ZnifferError> {
+        dst.extend_from_slice(&request.to_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec() {
+        let frame = Frame {
+            channel: 1,
+            timestamp: 0x1234,
+            rssi: 0xB0,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x0C, 0x0B, 0x02],
+            ..Default::default()
+        };
+
+        // A response and two frames in one read, the last one incomplete.
+        let mut src = BytesMut::new();
+        src.extend_from_slice(&[0x23, 0x04, 0x00]);
+        src.extend_from_slice(&frame.to_zniffer_vector());
+        src.extend_from_slice(&[0x21, 0x7F]);
+        let bytes = frame.to_zniffer_vector();
+        src.extend_from_slice(&bytes[..5]);
+
+        let mut codec = ZnifferCodec::new();
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Message::Command { id: 0x04, payload: vec![] }));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Message::Frame(frame.clone())));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(codec.invalid_frames(), 1);
+
+        src.extend_from_slice(&bytes[5..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Message::Frame(frame)));
+
+        let mut dst = BytesMut::new();
+        codec.encode(Request::Stop, &mut dst).unwrap();
+        assert_eq!(&dst[..], &[0x23, 0x05, 0x00]);
+    }
+}
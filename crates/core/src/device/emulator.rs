@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use std::time::Duration;
+use futures::StreamExt;
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+    AsyncWriteExt,
+};
+use tokio::time::MissedTickBehavior;
+use tokio_util::codec::FramedRead;
+use crate::types::{
+    Frame,
+    Region,
+};
+use crate::zniffer_command::{
+    CommandId,
+    Response,
+    Version,
+    ZnifferError,
+};
+use crate::zniffer_parser::SOF_FRAME;
+use super::codec::{
+    Message,
+    ZnifferCodec,
+};
+
+/// An invalid frame, i.e., a frame of an unknown type.
+pub const MALFORMED_FRAME: [u8; 3] = [SOF_FRAME, 0xFF, 0x00];
+
+/// Regions supported by the emulated device.
+const REGIONS: [Region; 12] = [
+    Region::EU,
+    Region::US,
+    Region::ANZ,
+    Region::HK,
+    Region::IN,
+    Region::IL,
+    Region::RU,
+    Region::CN,
+    Region::USLR,
+    Region::EULR,
+    Region::JP,
+    Region::KR,
+];
+
+/// A software Zniffer device.
+///
+/// Answers requests like a Zniffer device and, once started, replays a list
+/// of frames, e.g., read from a trace. It runs on the device end of a
+/// transport, e.g., the other end of `in_memory()` or a pseudo-terminal.
+#[derive(Debug, Clone)]
+pub struct Emulator {
+    version: Version,
+    region: Region,
+    lr_channel_config: u8,
+    frames: Vec<Frame>,
+    interval: Duration,
+    repeat: bool,
+    malformed_every: Option<usize>,
+    started: bool,
+}
+
+impl Emulator {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Emulator {
+            version: Version { chip_type: 0x08, chip_version: 0x00, major: 2, minor: 17 },
+            region: Region::default(),
+            lr_channel_config: 1,
+            frames,
+            interval: Duration::from_millis(100),
+            repeat: false,
+            malformed_every: None,
+            started: false,
+        }
+    }
+
+    /// Time between replayed frames. Defaults to 100 ms.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Start over with the first frame after the last one.
+    pub fn repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Send `MALFORMED_FRAME` before every `n`th frame.
+    pub fn malformed_every(mut self, n: usize) -> Self {
+        self.malformed_every = Some(n).filter(|n| *n > 0);
+        self
+    }
+
+    /// The region selected by the host.
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Whether the host started a capture.
+    pub fn started(&self) -> bool {
+        self.started
+    }
+
+    /// Handle a request, as returned by `ZnifferCodec` in
+    /// `Message::Command`.
+    pub fn respond(&mut self, id: u8, payload: &[u8]) -> Option<Response> {
+        let id = CommandId::try_from(id).ok()?;
+        let response = match (id, payload) {
+            (CommandId::GetVersion, _) => Response::Version(self.version.clone()),
+            (CommandId::SetRegion, [code, ..]) => {
+                if let Ok(region) = Region::try_from(*code) {
+                    self.region = region;
+                }
+                Response::Done(id)
+            },
+            (CommandId::GetRegions, _) => Response::Regions(REGIONS.iter().map(|r| *r as u8).collect()),
+            (CommandId::GetRegionName, [code, ..]) => match Region::try_from(*code) {
+                Ok(region) => Response::RegionName { channels: channels(region), name: format!("{:?}", region) },
+                Err(_) => Response::RegionName { channels: 0, name: String::new() },
+            },
+            (CommandId::Start, _) => {
+                self.started = true;
+                Response::Done(id)
+            },
+            (CommandId::Stop, _) => {
+                self.started = false;
+                Response::Done(id)
+            },
+            (CommandId::SetLrChannelConfig, [config, ..]) => {
+                self.lr_channel_config = *config;
+                Response::Done(id)
+            },
+            (CommandId::GetLrChannelConfigs, _) => {
+                Response::LrChannelConfigs { current: self.lr_channel_config, supported: vec![1, 2] }
+            },
+            (_, _) => Response::Done(id),
+        };
+        Some(response)
+    }
+
+    /// Serve a host until it disconnects.
+    pub async fn run<S: AsyncRead + AsyncWrite>(mut self, stream: S) -> Result<(), ZnifferError> {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut requests = FramedRead::new(reader, ZnifferCodec::new());
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut next = 0;
+        let mut sent = 0;
+
+        loop {
+            tokio::select! {
+                message = requests.next() => match message {
+                    Some(Ok(Message::Command { id, payload })) => {
+                        if let Some(response) = self.respond(id, &payload) {
+                            writer.write_all(&response.to_bytes()).await?;
+                        }
+                    },
+                    Some(Ok(Message::Frame(_))) => {
+                        // Hosts do not send frames.
+                    },
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                },
+                _ = ticker.tick(), if self.started => {
+                    if next == self.frames.len() && self.repeat {
+                        next = 0;
+                    }
+                    let Some(frame) = self.frames.get(next) else {
+                        continue;
+                    };
+                    sent += 1;
+                    if self.malformed_every.is_some_and(|n| sent % n == 0) {
+                        writer.write_all(&MALFORMED_FRAME).await?;
+                    }
+                    writer.write_all(&frame.to_zniffer_vector()).await?;
+                    next += 1;
+                },
+            }
+        }
+    }
+}
+
+/// Number of channels of a region.
+fn channels(region: Region) -> u8 {
+    match region {
+        Region::JP | Region::KR => 3,
+        Region::USLR | Region::EULR => 4,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{
+        in_memory,
+        Zniffer,
+    };
+
+    #[tokio::test]
+    async fn test_emulator() {
+        let frames: Vec<Frame> = (0..3u8)
+            .map(|i| Frame {
+                timestamp: i as u16,
+                payload: vec![0xE2, 0xEA, 0x36, 0xC3, i, 0x41, 0x0C, 0x0B, 0x02],
+                ..Default::default()
+            })
+            .collect();
+        let (host, device) = in_memory();
+        let emulator = Emulator::new(frames.clone())
+            .interval(Duration::from_millis(1))
+            .malformed_every(2);
+        let task = tokio::spawn(emulator.run(device));
+
+        let mut zniffer = Zniffer::new(host);
+        assert_eq!(zniffer.get_version().await.unwrap().major, 2);
+        assert!(zniffer.get_regions().await.unwrap().contains(&(Region::USLR as u8)));
+        assert_eq!(zniffer.get_region_name(Region::USLR as u8).await.unwrap(), ("USLR".to_string(), 4));
+        zniffer.set_region(Region::US).await.unwrap();
+        zniffer.start().await.unwrap();
+        for frame in &frames {
+            assert_eq!(&zniffer.next_frame().await.unwrap(), frame);
+        }
+        assert_eq!(zniffer.invalid_frames(), 1);
+        zniffer.stop().await.unwrap();
+
+        drop(zniffer);
+        assert!(task.await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,253 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Driver for Zniffer devices.
+//!
+//! `Zniffer` talks to a device over any `Transport`, e.g., a serial port, a
+//! TCP connection or an in-memory stream, and reads the Zniffer API with
+//! `ZnifferCodec`. `Emulator` plays the device, e.g., for tests without
+//! hardware.
+//!
+//! ```no_run
+//! # async fn capture() -> Result<(), zniff_rs_core::zniffer_command::ZnifferError> {
+//! use zniff_rs_core::device::{self, Zniffer};
+//! use zniff_rs_core::types::Region;
+//!
+//! let port = device::open_serial("/dev/ttyUSB0", device::DEFAULT_BAUD_RATE)?;
+//! let mut zniffer = Zniffer::new(port);
+//! zniffer.set_region(Region::EU).await?;
+//! zniffer.start().await?;
+//! let frame = zniffer.next_frame().await?;
+//! zniffer.stop().await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::time::Duration;
+use futures::{
+    SinkExt,
+    StreamExt,
+};
+use tokio::time::Instant;
+use tokio_util::codec::Framed;
+use crate::types::{
+    Frame,
+    Region,
+};
+use crate::zniffer_command::{
+    BaudRate,
+    CommandId,
+    Request,
+    Response,
+    Version,
+    ZnifferError,
+};
+
+mod codec;
+pub use codec::{
+    Message,
+    ZnifferCodec,
+};
+
+mod emulator;
+pub use emulator::{
+    Emulator,
+    MALFORMED_FRAME,
+};
+
+mod transport;
+pub use transport::{
+    connect_tcp,
+    in_memory,
+    open_serial,
+    Transport,
+    DEFAULT_BAUD_RATE,
+};
+#[cfg(unix)]
+pub use transport::pseudo_terminal;
+pub use tokio_serial::SerialStream;
+
+/// Time to wait for the response to a request.
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A pause after which a partially received message is discarded.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub struct Zniffer<T: Transport> {
+    framed: Framed<T, ZnifferCodec>,
+    /// Frames received while waiting for a response.
+    pending: VecDeque<Frame>,
+}
+
+impl<T: Transport> Zniffer<T> {
+    pub fn new(transport: T) -> Self {
+        Zniffer {
+            framed: Framed::new(transport, ZnifferCodec::new()),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Number of invalid frames received so far.
+    pub fn invalid_frames(&self) -> usize {
+        self.framed.codec().invalid_frames()
+    }
+
+    async fn read_message(&mut self) -> Result<Message, ZnifferError> {
+        loop {
+            match tokio::time::timeout(IDLE_TIMEOUT, self.framed.next()).await {
+                Ok(Some(message)) => return message,
+                Ok(None) => return Err(ZnifferError::Closed),
+                Err(_) => self.framed.codec_mut().timeout(),
+            }
+        }
+    }
+
+    /// Send a request and wait for the response with the same command ID.
+    /// Frames received in the meantime are returned by `next_frame()`.
+    pub async fn request(&mut self, request: Request) -> Result<Response, ZnifferError> {
+        let id = request.id();
+        self.framed.send(request).await?;
+
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            let message = tokio::time::timeout_at(deadline, self.read_message())
+                .await
+                .map_err(|_| ZnifferError::Timeout(id))??;
+            match message {
+                Message::Command { id: response_id, payload } if response_id == id as u8 => {
+                    return Response::parse(id, &payload);
+                },
+                Message::Command { .. } => {
+                    // A late response to an earlier request.
+                },
+                Message::Frame(frame) => {
+                    self.pending.push_back(frame);
+                },
+            }
+        }
+    }
+
+    pub async fn get_version(&mut self) -> Result<Version, ZnifferError> {
+        match self.request(Request::GetVersion).await? {
+            Response::Version(version) => Ok(version),
+            _ => Err(ZnifferError::InvalidResponse(CommandId::GetVersion)),
+        }
+    }
+
+    pub async fn set_region(&mut self, region: Region) -> Result<(), ZnifferError> {
+        self.request(Request::SetRegion(region)).await.map(|_| ())
+    }
+
+    /// Codes of the regions supported by the device.
+    pub async fn get_regions(&mut self) -> Result<Vec<u8>, ZnifferError> {
+        match self.request(Request::GetRegions).await? {
+            Response::Regions(codes) => Ok(codes),
+            _ => Err(ZnifferError::InvalidResponse(CommandId::GetRegions)),
+        }
+    }
+
+    /// Name and number of channels of a region code.
+    pub async fn get_region_name(&mut self, code: u8) -> Result<(String, u8), ZnifferError> {
+        match self.request(Request::GetRegionName(code)).await? {
+            Response::RegionName { channels, name } => Ok((name, channels)),
+            _ => Err(ZnifferError::InvalidResponse(CommandId::GetRegionName)),
+        }
+    }
+
+    /// The current and the supported Long Range channel configurations.
+    pub async fn get_lr_channel_configs(&mut self) -> Result<(u8, Vec<u8>), ZnifferError> {
+        match self.request(Request::GetLrChannelConfigs).await? {
+            Response::LrChannelConfigs { current, supported } => Ok((current, supported)),
+            _ => Err(ZnifferError::InvalidResponse(CommandId::GetLrChannelConfigs)),
+        }
+    }
+
+    pub async fn set_lr_channel_config(&mut self, config: u8) -> Result<(), ZnifferError> {
+        self.request(Request::SetLrChannelConfig(config)).await.map(|_| ())
+    }
+
+    /// The Zniffer API has no query for the baud rate, so report the rate of
+    /// the transport, if it has one.
+    pub fn baud_rate(&self) -> Option<u32> {
+        self.framed.get_ref().baud_rate()
+    }
+
+    /// Switch the device and then the transport to a new baud rate.
+    pub async fn set_baud_rate(&mut self, baud_rate: BaudRate) -> Result<(), ZnifferError> {
+        self.request(Request::SetBaudRate(baud_rate)).await?;
+        self.framed.get_mut().set_baud_rate(baud_rate.bits_per_second())?;
+        Ok(())
+    }
+
+    pub async fn start(&mut self) -> Result<(), ZnifferError> {
+        self.request(Request::Start).await.map(|_| ())
+    }
+
+    pub async fn stop(&mut self) -> Result<(), ZnifferError> {
+        self.request(Request::Stop).await.map(|_| ())
+    }
+
+    /// Wait for the next frame. Returns `ZnifferError::Stopped` for a Stop
+    /// response outside of `request()`.
+    ///
+    /// Cancel safe, e.g., to race it against a shutdown signal and then call
+    /// `stop()`.
+    pub async fn next_frame(&mut self) -> Result<Frame, ZnifferError> {
+        if let Some(frame) = self.pending.pop_front() {
+            return Ok(frame);
+        }
+
+        loop {
+            match self.read_message().await? {
+                Message::Frame(frame) => return Ok(frame),
+                Message::Command { id, .. } if id == CommandId::Stop as u8 => {
+                    return Err(ZnifferError::Stopped);
+                },
+                Message::Command { .. } => {
+                    // The device does not send unsolicited commands.
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+
+    #[tokio::test]
+    async fn test_zniffer() {
+        let (host, mut device) = in_memory();
+        let frame = Frame {
+            timestamp: 0x0102,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x0C, 0x0B, 0x02],
+            ..Default::default()
+        };
+
+        let bytes = frame.to_zniffer_vector();
+        tokio::spawn(async move {
+            let mut request = [0u8; 3];
+            device.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [0x23, 0x01, 0x00]);
+            // A frame received before the response, in the same write.
+            let mut response = bytes.clone();
+            response.extend_from_slice(&[0x23, 0x01, 0x04, 0x08, 0x00, 0x02, 0x11]);
+            device.write_all(&response).await.unwrap();
+
+            device.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [0x23, 0x05, 0x00]);
+        });
+
+        let mut zniffer = Zniffer::new(host);
+        let version = zniffer.get_version().await.unwrap();
+        assert_eq!(version.major, 2);
+        assert_eq!(zniffer.next_frame().await.unwrap(), frame);
+        assert!(zniffer.baud_rate().is_none());
+
+        // The device does not answer Stop and disconnects.
+        assert!(matches!(zniffer.stop().await, Err(ZnifferError::Closed)));
+    }
+}
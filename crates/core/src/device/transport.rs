@@ -0,0 +1,78 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use std::io;
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+    DuplexStream,
+};
+use tokio::net::{
+    TcpStream,
+    ToSocketAddrs,
+};
+use tokio_serial::{
+    SerialPort,
+    SerialPortBuilderExt,
+    SerialStream,
+};
+
+/// Default baud rate of a Zniffer device.
+pub const DEFAULT_BAUD_RATE: u32 = 230_400;
+
+/// A byte stream connected to a Zniffer device.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send {
+    /// Line speed of the transport, if it has one.
+    fn baud_rate(&self) -> Option<u32> {
+        None
+    }
+
+    /// Change the line speed after the device switched to a new baud rate.
+    /// Does nothing for transports without a line speed.
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for SerialStream {
+    fn baud_rate(&self) -> Option<u32> {
+        SerialPort::baud_rate(self).ok()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        SerialPort::set_baud_rate(self, baud_rate).map_err(io::Error::from)
+    }
+}
+
+/// A Zniffer device shared over the network, e.g., by `ser2net`.
+impl Transport for TcpStream {}
+
+/// One end of `in_memory()`, e.g., connected to an emulated device.
+impl Transport for DuplexStream {}
+
+/// Open a serial port, e.g., COM3 or /dev/ttyUSB0.
+pub fn open_serial(path: &str, baud_rate: u32) -> io::Result<SerialStream> {
+    let port = tokio_serial::new(path, baud_rate).open_native_async()?;
+    Ok(port)
+}
+
+/// A pseudo-terminal for an emulated device. Returns the device end and the
+/// path of the host end, which can be opened with `open_serial()`, e.g., by
+/// another process.
+///
+/// Reading the device end fails while no host has the path open.
+#[cfg(unix)]
+pub fn pseudo_terminal() -> io::Result<(SerialStream, String)> {
+    let (device, host) = SerialStream::pair()?;
+    let path = host.name().ok_or_else(|| io::Error::other("pseudo-terminal without a path"))?;
+    Ok((device, path))
+}
+
+pub async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> {
+    TcpStream::connect(addr).await
+}
+
+/// A pair of connected in-memory transports. Bytes written to one end can
+/// be read from the other.
+pub fn in_memory() -> (DuplexStream, DuplexStream) {
+    tokio::io::duplex(4096)
+}
@@ -1,6 +1,12 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
+pub mod beam;
+pub mod decoder;
+pub mod device;
+pub mod mac;
 pub mod zlf;
 pub mod storage;
 pub mod types;
 pub mod zniffer_parser;
+pub mod zniffer_command;
+pub mod pti_parser;
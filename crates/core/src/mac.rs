@@ -0,0 +1,633 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Z-Wave MAC headers, i.e., the start of the MPDU of a data frame.
+//!
+//! ```text
+//! 2-channel (BASIC):   [ home_id(4), src, fc1, fc2, len, dst ]
+//! 3-channel (BASIC24): [ home_id(4), src, fc1, fc2, len, seq, dst ]
+//! Long Range (BASICLR): [ home_id(4), src << 12 | dst (3), len, fc, seq, noise_floor, tx_power ]
+//!
+//! 2-channel fc1: routed << 7 | ack << 6 | low_power << 5 | speed_modified << 4 | header_type
+//! 2-channel fc2: suc_present << 7 | beam_1000ms << 6 | beam_250ms << 5 | seq
+//! 3-channel fc1: ack << 7 | low_power << 6 | suc_present << 5 | speed_modified << 4 | header_type
+//! 3-channel fc2: extended << 7 | source_wakeup << 4
+//! Long Range fc:  ack << 7 | extended << 6 | header_type
+//! ```
+//!
+//! Multicast headers have no destination node ID but a destination bitmask:
+//!
+//! ```text
+//! [ address_offset << 5 | mask_length, mask(mask_length) ]
+//! ```
+//!
+//! Bit `n` of the mask addresses node `address_offset * 256 + n + 1`.
+//! Broadcasts are singlecasts to node `0xFF`, or `0xFFF` on Long Range.
+//! Long Range acknowledgements carry the RSSI of the acknowledged frame.
+//!
+//! Routed singlecasts, i.e., 2-channel singlecasts with the routed flag and
+//! 3-channel frames of type `Routed`, continue with a source routing header:
+//!
+//! ```text
+//! [ dst, properties, repeater_count << 4 | hop, repeaters(repeater_count) ]
+//!
+//! properties: failed_hop << 4 | extended << 3 | error << 2 | ack << 1 | inbound
+//! ```
+//!
+//! Only routed errors have a failed hop, other frames use these bits for
+//! flags.
+//!
+//! Explorer frames, which flood the network, e.g., for inclusion and route
+//! discovery, continue with an explorer header:
+//!
+//! ```text
+//! [ dst, version << 5 | command, stop_request << 3 | stop << 2 | inbound << 1 | source_routed,
+//!   session_tx_random_interval, ttl << 4 | repeater_count, repeaters(4), command fields ]
+//!
+//! search result fields:  [ node_id, frame_handle, ttl << 4 | repeater_count, repeaters(4) ]
+//! autoinclusion fields:  [ network_home_id(4) ]
+//! ```
+//!
+//! The MPDU ends with an XOR checksum at 9.6 and 40 kbps, and with a CRC-16
+//! (CRC-16/AUG-CCITT) at 100 kbps and on Long Range, see `checksum_ok()`.
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crc16::{
+    State,
+    AUG_CCITT,
+};
+use thiserror::Error;
+use crate::types::{
+    Frame,
+    Region,
+    SPEED_LONG_RANGE,
+};
+
+/// Destination node ID of broadcasts.
+pub const BROADCAST_NODE_ID: u16 = 0xFF;
+
+/// Destination node ID of Long Range broadcasts.
+pub const BROADCAST_NODE_ID_LR: u16 = 0xFFF;
+
+/// Base header layout, see `BaseHeader` in FrameDefinition.xml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Layout {
+    /// 2-channel regions (BASIC).
+    TwoChannel,
+    /// 3-channel regions, e.g., JP and KR (BASIC24).
+    ThreeChannel,
+    /// Long Range channels of the LR regions (BASICLR).
+    LongRange,
+}
+
+impl Layout {
+    /// Layout of the classic channels of a region.
+    pub fn for_region(region: Region) -> Self {
+        match region {
+            Region::JP | Region::KR => Layout::ThreeChannel,
+            _ => Layout::TwoChannel,
+        }
+    }
+
+    /// Layout of a frame received in `region` at `speed`. Long Range frames
+    /// are told apart by their speed, since LR regions also use the classic
+    /// channels.
+    pub fn for_frame(region: Region, speed: u8) -> Self {
+        if speed == SPEED_LONG_RANGE {
+            Layout::LongRange
+        } else {
+            Layout::for_region(region)
+        }
+    }
+
+    /// Size of the base header in bytes.
+    fn base_size(self) -> usize {
+        match self {
+            Layout::TwoChannel => 8,
+            Layout::ThreeChannel => 9,
+            Layout::LongRange => 12,
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeaderType {
+    Singlecast = 0x01,
+    Multicast = 0x02,
+    TransferAcknowledge = 0x03,
+    Flooded = 0x04,
+    Explorer = 0x05,
+    /// 3-channel routed frames. 2-channel frames use the routed flag instead.
+    Routed = 0x08,
+}
+
+impl TryFrom<u8> for HeaderType {
+    type Error = MacError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(HeaderType::Singlecast),
+            0x02 => Ok(HeaderType::Multicast),
+            0x03 => Ok(HeaderType::TransferAcknowledge),
+            0x04 => Ok(HeaderType::Flooded),
+            0x05 => Ok(HeaderType::Explorer),
+            0x08 => Ok(HeaderType::Routed),
+            _ => Err(MacError::InvalidHeaderType(value)),
+        }
+    }
+}
+
+/// Fields only found in Long Range headers, in dBm.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LongRangeFields {
+    pub noise_floor: i8,
+    pub tx_power: i8,
+    /// RSSI of the acknowledged frame, only in acknowledgements.
+    pub received_rssi: Option<i8>,
+}
+
+/// Direction of a routed frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// From the source along the repeaters to the destination.
+    Outbound,
+    /// Back along the repeaters, i.e., routed acknowledgements and errors.
+    Inbound,
+}
+
+/// Source routing header of a routed singlecast, acknowledgement or error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Route {
+    pub direction: Direction,
+    /// A routed acknowledgement.
+    pub ack: bool,
+    /// A routed error, sent back by the repeater that failed to reach the
+    /// next hop.
+    pub error: bool,
+    /// Index of the hop that failed, only in routed errors.
+    pub failed_hop: Option<u8>,
+    /// Hop counter, i.e., the index of the repeater that sent the frame.
+    pub hop: u8,
+    /// Node IDs of the repeaters, from the source to the destination.
+    pub repeaters: Vec<u8>,
+}
+
+impl Route {
+    /// Decode the routing header that follows the destination node ID.
+    fn decode(fields: &[u8]) -> Option<Self> {
+        let [properties, hops, repeaters @ ..] = fields else {
+            return None;
+        };
+        let error = properties & 0x04 != 0;
+        Some(Route {
+            direction: if properties & 0x01 != 0 { Direction::Inbound } else { Direction::Outbound },
+            ack: properties & 0x02 != 0,
+            error,
+            failed_hop: error.then_some(properties >> 4),
+            hop: hops & 0x0F,
+            repeaters: repeaters.get(..(hops >> 4) as usize)?.to_vec(),
+        })
+    }
+}
+
+/// Number of repeater slots in explorer headers.
+const EXPLORER_REPEATERS: usize = 4;
+
+/// Command of an explorer frame, see `ExploreCommandType` in
+/// FrameDefinition.xml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExploreCommand {
+    Normal,
+    /// Explorer frame of a node that requests to be included.
+    Autoinclusion {
+        network_home_id: u32,
+    },
+    /// The route found by a search, returned to the node that searched.
+    SearchResult {
+        node_id: u8,
+        frame_handle: u8,
+        ttl: u8,
+        repeaters: Vec<u8>,
+    },
+}
+
+/// Explorer header of an explorer frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Explorer {
+    pub command: ExploreCommand,
+    pub version: u8,
+    pub source_routed: bool,
+    pub direction: Direction,
+    pub stop: bool,
+    pub stop_request: bool,
+    pub session_tx_random_interval: u8,
+    /// Number of hops the frame may still take.
+    pub ttl: u8,
+    /// Node IDs of the repeaters the frame has taken so far.
+    pub repeaters: Vec<u8>,
+}
+
+impl Explorer {
+    /// Decode the explorer header that follows the destination node ID of an
+    /// MPDU of `mpdu_len` bytes.
+    fn decode(fields: &[u8], mpdu_len: usize) -> Result<Self, MacError> {
+        let truncated = MacError::Truncated(mpdu_len);
+        let ([properties, flags, interval, hops], rest) = fields.split_first_chunk::<4>().ok_or(truncated.clone())?;
+        let (repeaters, rest) = rest.split_at_checked(EXPLORER_REPEATERS).ok_or(truncated.clone())?;
+
+        let command = match properties & 0x1F {
+            0x00 => ExploreCommand::Normal,
+            0x01 => {
+                let home_id = rest.first_chunk::<4>().ok_or(truncated)?;
+                ExploreCommand::Autoinclusion { network_home_id: u32::from_be_bytes(*home_id) }
+            },
+            0x02 => match rest {
+                [node_id, frame_handle, hops, repeaters @ ..] if repeaters.len() >= EXPLORER_REPEATERS => {
+                    ExploreCommand::SearchResult {
+                        node_id: *node_id,
+                        frame_handle: *frame_handle,
+                        ttl: hops >> 4,
+                        repeaters: explorer_repeaters(repeaters, *hops),
+                    }
+                },
+                _ => return Err(truncated),
+            },
+            other => return Err(MacError::InvalidExploreCommand(other)),
+        };
+        Ok(Explorer {
+            command,
+            version: properties >> 5,
+            source_routed: flags & 0x01 != 0,
+            direction: if flags & 0x02 != 0 { Direction::Inbound } else { Direction::Outbound },
+            stop: flags & 0x04 != 0,
+            stop_request: flags & 0x08 != 0,
+            session_tx_random_interval: *interval,
+            ttl: hops >> 4,
+            repeaters: explorer_repeaters(repeaters, *hops),
+        })
+    }
+}
+
+/// The used repeater slots of an explorer header.
+fn explorer_repeaters(slots: &[u8], hops: u8) -> Vec<u8> {
+    let count = ((hops & 0x0F) as usize).min(EXPLORER_REPEATERS);
+    slots[..count].to_vec()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacHeader {
+    pub layout: Layout,
+    pub home_id: u32,
+    /// 8-bit, or 12-bit on Long Range.
+    pub src_node_id: u16,
+    /// None for multicasts.
+    pub dst_node_id: Option<u16>,
+    pub header_type: HeaderType,
+    /// The sender requests an acknowledgement.
+    pub ack: bool,
+    pub routed: bool,
+    pub low_power: bool,
+    pub speed_modified: bool,
+    pub sequence_number: u8,
+    /// Length of the MPDU according to the header.
+    pub length: u8,
+    /// None unless the layout is `Layout::LongRange`.
+    pub long_range: Option<LongRangeFields>,
+    /// None unless the frame is a routed singlecast, acknowledgement or
+    /// error.
+    pub route: Option<Route>,
+    /// Node IDs addressed by a multicast, None for other frames.
+    pub multicast: Option<Vec<u16>>,
+    /// None unless the frame is an explorer frame.
+    pub explorer: Option<Explorer>,
+}
+
+impl MacHeader {
+    /// Decode the MAC header at the start of an MPDU.
+    pub fn decode(mpdu: &[u8], layout: Layout) -> Result<Self, MacError> {
+        let (base, fields) = mpdu
+            .split_at_checked(layout.base_size())
+            .ok_or(MacError::Truncated(mpdu.len()))?;
+        let first_field = || fields.first().copied().ok_or(MacError::Truncated(mpdu.len()));
+
+        let home_id = u32::from_be_bytes([base[0], base[1], base[2], base[3]]);
+        let header_type = match layout {
+            Layout::LongRange => HeaderType::try_from(base[8] & 0x07)?,
+            _ => HeaderType::try_from(base[5] & 0x0F)?,
+        };
+        let dst_node_id = match (layout, header_type) {
+            (Layout::LongRange, _) => Some(u16::from_be_bytes([base[5], base[6]]) & 0x0FFF),
+            (_, HeaderType::Multicast) => None,
+            _ => Some(first_field()? as u16),
+        };
+
+        let header = match layout {
+            Layout::TwoChannel => MacHeader {
+                layout,
+                home_id,
+                src_node_id: base[4] as u16,
+                dst_node_id,
+                header_type,
+                ack: base[5] & 0x40 != 0,
+                routed: base[5] & 0x80 != 0,
+                low_power: base[5] & 0x20 != 0,
+                speed_modified: base[5] & 0x10 != 0,
+                sequence_number: base[6] & 0x0F,
+                length: base[7],
+                long_range: None,
+                route: None,
+                multicast: None,
+                explorer: None,
+            },
+            Layout::ThreeChannel => MacHeader {
+                layout,
+                home_id,
+                src_node_id: base[4] as u16,
+                dst_node_id,
+                header_type,
+                ack: base[5] & 0x80 != 0,
+                routed: header_type == HeaderType::Routed,
+                low_power: base[5] & 0x40 != 0,
+                speed_modified: base[5] & 0x10 != 0,
+                sequence_number: base[8],
+                length: base[7],
+                long_range: None,
+                route: None,
+                multicast: None,
+                explorer: None,
+            },
+            Layout::LongRange => MacHeader {
+                layout,
+                home_id,
+                src_node_id: u16::from_be_bytes([base[4], base[5]]) >> 4,
+                dst_node_id,
+                header_type,
+                ack: base[8] & 0x80 != 0,
+                routed: false,
+                low_power: false,
+                speed_modified: false,
+                sequence_number: base[9],
+                length: base[7],
+                long_range: Some(LongRangeFields {
+                    noise_floor: base[10] as i8,
+                    tx_power: base[11] as i8,
+                    received_rssi: match header_type {
+                        HeaderType::TransferAcknowledge => Some(first_field()? as i8),
+                        _ => None,
+                    },
+                }),
+                route: None,
+                multicast: None,
+                explorer: None,
+            },
+        };
+
+        let routed = match layout {
+            Layout::TwoChannel => header.routed && header_type == HeaderType::Singlecast,
+            _ => header_type == HeaderType::Routed,
+        };
+        if routed {
+            let route = Route::decode(&fields[1..]).ok_or(MacError::Truncated(mpdu.len()))?;
+            return Ok(MacHeader { route: Some(route), ..header });
+        }
+        if layout != Layout::LongRange && header_type == HeaderType::Multicast {
+            let multicast = decode_multicast(fields).ok_or(MacError::Truncated(mpdu.len()))?;
+            return Ok(MacHeader { multicast: Some(multicast), ..header });
+        }
+        if layout != Layout::LongRange && header_type == HeaderType::Explorer {
+            let explorer = Explorer::decode(&fields[1..], mpdu.len())?;
+            return Ok(MacHeader { explorer: Some(explorer), ..header });
+        }
+        Ok(header)
+    }
+
+    /// Whether the frame is addressed to `node_id`, directly or by a
+    /// multicast. Broadcasts only match the broadcast node ID.
+    pub fn is_addressed_to(&self, node_id: u16) -> bool {
+        self.dst_node_id == Some(node_id) || self.multicast.as_ref().is_some_and(|nodes| nodes.contains(&node_id))
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        match self.layout {
+            Layout::LongRange => self.dst_node_id == Some(BROADCAST_NODE_ID_LR),
+            _ => self.dst_node_id == Some(BROADCAST_NODE_ID),
+        }
+    }
+}
+
+/// Verify the checksum at the end of an MPDU received at `speed`.
+pub fn checksum_ok(mpdu: &[u8], speed: u8) -> bool {
+    match speed {
+        // 9.6 and 40 kbps
+        0 | 1 => match mpdu.split_last() {
+            Some((checksum, data)) => data.iter().fold(0xFF, |xor, byte| xor ^ byte) == *checksum,
+            None => false,
+        },
+        _ => match mpdu.split_last_chunk::<2>() {
+            Some((data, crc)) => State::<AUG_CCITT>::calculate(data) == u16::from_be_bytes(*crc),
+            None => false,
+        },
+    }
+}
+
+/// Decode the destination bitmask of a multicast into node IDs.
+fn decode_multicast(fields: &[u8]) -> Option<Vec<u16>> {
+    let (properties, fields) = fields.split_first()?;
+    let mask = fields.get(..(properties & 0x1F) as usize)?;
+    let first_node_id = (properties >> 5) as u16 * 256 + 1;
+    let node_ids = mask
+        .iter()
+        .enumerate()
+        .flat_map(|(i, byte)| (0..8).filter(move |bit| byte & (1 << bit) != 0).map(move |bit| i as u16 * 8 + bit))
+        .map(|n| first_node_id + n)
+        .collect();
+    Some(node_ids)
+}
+
+impl Frame {
+    /// Decode the MAC header of a data frame, using the layout of its region
+    /// and speed.
+    pub fn mac_header(&self) -> Result<MacHeader, MacError> {
+        MacHeader::decode(&self.payload, Layout::for_frame(self.region, self.speed))
+    }
+
+    /// Verify the checksum of a data frame.
+    pub fn checksum_ok(&self) -> bool {
+        checksum_ok(&self.payload, self.speed)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MacError {
+    #[error("MAC header truncated after {0} bytes")]
+    Truncated(usize),
+    #[error("invalid header type 0x{0:X}")]
+    InvalidHeaderType(u8),
+    #[error("invalid explore command type 0x{0:X}")]
+    InvalidExploreCommand(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_channel() {
+        // Singlecast from node 1 to node 0x0B with ack request, sequence 3.
+        let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x03, 0x0D, 0x0B, 0x25, 0x01, 0xFF, 0x5A];
+        let header = MacHeader::decode(&mpdu, Layout::TwoChannel).unwrap();
+        assert_eq!(header.home_id, 0xE2EA36C3);
+        assert_eq!(header.src_node_id, 1);
+        assert_eq!(header.dst_node_id, Some(0x0B));
+        assert_eq!(header.header_type, HeaderType::Singlecast);
+        assert!(header.ack && !header.routed && !header.low_power);
+        assert_eq!(header.sequence_number, 3);
+        assert_eq!(header.length, 0x0D);
+
+        // Acknowledgement of a routed frame by the first repeater.
+        let header = MacHeader::decode(&[0xE2, 0xEA, 0x36, 0xC3, 0x05, 0x83, 0x05, 0x0A, 0x01], Layout::TwoChannel).unwrap();
+        assert_eq!(header.header_type, HeaderType::TransferAcknowledge);
+        assert!(header.routed && !header.ack);
+        assert_eq!(header.route, None);
+
+        // Multicast to nodes 2, 3 and 12, which has no destination node ID.
+        let header = MacHeader::decode(&[0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x02, 0x00, 0x0F, 0x02, 0x06, 0x08, 0x25, 0x01, 0xFF], Layout::TwoChannel).unwrap();
+        assert_eq!(header.dst_node_id, None);
+        assert_eq!(header.multicast, Some(vec![2, 3, 12]));
+        assert!(header.is_addressed_to(12) && !header.is_addressed_to(1));
+        assert_eq!(MacHeader::decode(&[0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x02, 0x00, 0x0F, 0x02, 0x06], Layout::TwoChannel), Err(MacError::Truncated(10)));
+
+        assert_eq!(MacHeader::decode(&mpdu[..8], Layout::TwoChannel), Err(MacError::Truncated(8)));
+        assert_eq!(MacHeader::decode(&[0; 9], Layout::TwoChannel), Err(MacError::InvalidHeaderType(0)));
+    }
+
+    #[test]
+    fn test_three_channel() {
+        let frame = Frame {
+            region: Region::JP,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x88, 0x00, 0x0E, 0x7F, 0x05, 0x03, 0x10, 0x02, 0xBE, 0xEF],
+            ..Default::default()
+        };
+        let header = frame.mac_header().unwrap();
+        assert_eq!(header.layout, Layout::ThreeChannel);
+        assert_eq!(header.header_type, HeaderType::Routed);
+        assert!(header.ack && header.routed);
+        assert_eq!(header.sequence_number, 0x7F);
+        assert_eq!(header.dst_node_id, Some(5));
+        // Routed acknowledgement from node 1 back to node 5 over node 2.
+        let route = header.route.unwrap();
+        assert_eq!(route.direction, Direction::Inbound);
+        assert!(route.ack && !route.error);
+        assert_eq!((route.hop, route.repeaters), (0, vec![0x02]));
+
+    }
+
+    #[test]
+    fn test_routed() {
+        // Singlecast from node 1 to node 0x0B over nodes 5 and 7, sent by node 5.
+        let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x01, 0xC1, 0x05, 0x12, 0x0B, 0x00, 0x21, 0x05, 0x07, 0x25, 0x02, 0x00];
+        let header = MacHeader::decode(&mpdu, Layout::TwoChannel).unwrap();
+        let route = header.route.unwrap();
+        assert_eq!(route.direction, Direction::Outbound);
+        assert!(!route.ack && !route.error);
+        assert_eq!(route.hop, 1);
+        assert_eq!(route.repeaters, vec![0x05, 0x07]);
+
+        // Routed error from node 5, which failed to reach node 7.
+        let mpdu = [0xE2, 0xEA, 0x36, 0xC3, 0x05, 0x81, 0x05, 0x0F, 0x01, 0x15, 0x20, 0x05, 0x07, 0x00];
+        let route = MacHeader::decode(&mpdu, Layout::TwoChannel).unwrap().route.unwrap();
+        assert_eq!(route.direction, Direction::Inbound);
+        assert!(route.error);
+        assert_eq!(route.failed_hop, Some(1));
+
+        assert_eq!(MacHeader::decode(&mpdu[..12], Layout::TwoChannel), Err(MacError::Truncated(12)));
+    }
+
+    #[test]
+    fn test_explorer() {
+        // Search result from node 0x0B back to node 1 over node 5, with the
+        // route from node 1 to node 0x20 over nodes 5 and 0x0B.
+        let mpdu = [
+            0xE2, 0xEA, 0x36, 0xC3, 0x0B, 0x05, 0x07, 0x1A, 0x01,
+            0x22, 0x02, 0x40, 0x31, 0x05, 0x00, 0x00, 0x00,
+            0x20, 0x7A, 0x02, 0x05, 0x0B, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let header = MacHeader::decode(&mpdu, Layout::TwoChannel).unwrap();
+        let explorer = header.explorer.unwrap();
+        assert_eq!(explorer.version, 1);
+        assert_eq!(explorer.direction, Direction::Inbound);
+        assert!(!explorer.stop && !explorer.source_routed);
+        assert_eq!(explorer.session_tx_random_interval, 0x40);
+        assert_eq!((explorer.ttl, explorer.repeaters), (3, vec![0x05]));
+        assert_eq!(
+            explorer.command,
+            ExploreCommand::SearchResult { node_id: 0x20, frame_handle: 0x7A, ttl: 0, repeaters: vec![0x05, 0x0B] },
+        );
+
+        // Autoinclusion request, not repeated yet.
+        let mpdu = [
+            0xE2, 0xEA, 0x36, 0xC3, 0x00, 0x05, 0x01, 0x15, 0xFF,
+            0x21, 0x04, 0x40, 0x40, 0x00, 0x00, 0x00, 0x00,
+            0xC0, 0xFF, 0xEE, 0x00,
+        ];
+        let explorer = MacHeader::decode(&mpdu, Layout::TwoChannel).unwrap().explorer.unwrap();
+        assert!(explorer.stop);
+        assert_eq!(explorer.command, ExploreCommand::Autoinclusion { network_home_id: 0xC0FFEE00 });
+        assert!(explorer.repeaters.is_empty());
+
+        assert_eq!(MacHeader::decode(&mpdu[..20], Layout::TwoChannel), Err(MacError::Truncated(20)));
+        let mut invalid = mpdu;
+        invalid[9] = 0x23;
+        assert_eq!(MacHeader::decode(&invalid, Layout::TwoChannel), Err(MacError::InvalidExploreCommand(3)));
+    }
+
+    #[test]
+    fn test_long_range() {
+        // Singlecast from node 0x101 to node 0x00A, noise floor -95 dBm, TX power 14 dBm.
+        let frame = Frame {
+            region: Region::USLR,
+            speed: SPEED_LONG_RANGE,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x10, 0x10, 0x0A, 0x10, 0x81, 0x2A, 0xA1, 0x0E, 0x25, 0x02, 0x00, 0xC5],
+            ..Default::default()
+        };
+        let header = frame.mac_header().unwrap();
+        assert_eq!(header.layout, Layout::LongRange);
+        assert_eq!(header.src_node_id, 0x101);
+        assert_eq!(header.dst_node_id, Some(0x00A));
+        assert_eq!(header.header_type, HeaderType::Singlecast);
+        assert!(header.ack && !header.is_broadcast());
+        assert_eq!(header.sequence_number, 0x2A);
+        assert_eq!(header.long_range, Some(LongRangeFields { noise_floor: -95, tx_power: 14, received_rssi: None }));
+
+        // Acknowledgement with the RSSI of the acknowledged frame.
+        let header = MacHeader::decode(&[0xE2, 0xEA, 0x36, 0xC3, 0x00, 0xA1, 0x01, 0x0E, 0x03, 0x2A, 0xA0, 0x0A, 0xB8, 0x00, 0x00], Layout::LongRange).unwrap();
+        assert_eq!(header.header_type, HeaderType::TransferAcknowledge);
+        assert_eq!(header.long_range.unwrap().received_rssi, Some(-72));
+
+        let broadcast = MacHeader::decode(&[0xE2, 0xEA, 0x36, 0xC3, 0x10, 0x1F, 0xFF, 0x0C, 0x01, 0x00, 0xA1, 0x0E], Layout::LongRange).unwrap();
+        assert!(broadcast.is_broadcast());
+        assert_eq!(MacHeader::decode(&[0; 11], Layout::LongRange), Err(MacError::Truncated(11)));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let mut mpdu = vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x03, 0x0D, 0x0B, 0x25, 0x01, 0xFF, 0x9C];
+        assert!(checksum_ok(&mpdu, 0));
+        assert!(checksum_ok(&mpdu, 1));
+        assert!(!checksum_ok(&mpdu, 2));
+        mpdu[10] = 0x00;
+        assert!(!checksum_ok(&mpdu, 0));
+
+        let frame = Frame {
+            region: Region::USLR,
+            speed: SPEED_LONG_RANGE,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x10, 0x10, 0x0A, 0x10, 0x81, 0x2A, 0xA1, 0x0E, 0x25, 0x02, 0x39, 0x6E],
+            ..Default::default()
+        };
+        assert!(frame.checksum_ok());
+        assert!(!checksum_ok(&[], 0));
+        assert!(!checksum_ok(&[0x1D], 2));
+    }
+}
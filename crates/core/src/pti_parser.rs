@@ -0,0 +1,277 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Parser for Silicon Labs PTI frames wrapped in DCH (debug channel) framing,
+//! as stored in ZLF PTI (0xF5) records and served by `zniff-rs-cli server`.
+//!
+//! A DCH frame looks like:
+//!
+//! ```text
+//! [ 5b, len_lo, len_hi, <DCH header>, F8, <MPDU>, F9, rssi, region, channel, protocol, status, 5d ]
+//! ```
+//!
+//! where the length counts everything but the `[` and `]` delimiters, and the
+//! DCH header is 11 bytes for DCH version 2 and 18 bytes for version 3. Both
+//! start with the 2-byte version followed by the timestamp (48 bit
+//! microseconds for version 2, 64 bit nanoseconds for version 3).
+use crate::beam::BEAM_TAG;
+use crate::types::{
+  Frame,
+  FrameType,
+  PtiRegion,
+  Region,
+};
+
+const DCH_START: u8 = 0x5B;
+const DCH_END: u8 = 0x5D;
+const DCH_V2_HEADER_LENGTH: usize = 11;
+const DCH_V3_HEADER_LENGTH: usize = 18;
+
+const PTI_RX_START: u8 = 0xF8;
+const PTI_RX_END: u8 = 0xF9;
+const PTI_TX_START: u8 = 0xFC;
+const PTI_TX_END: u8 = 0xFD;
+
+const PROTOCOL_ZWAVE: u8 = 0x06;
+
+/// Bytes following the MPDU: end marker, RSSI, region, channel, protocol, status and `]`.
+const AFTER_DATA_LENGTH: usize = 7;
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum ParserResult {
+  ValidFrame {
+    frame: Frame,
+  },
+  IncompleteFrame,
+  InvalidFrame,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+enum ParserState {
+  Idle,
+  AwaitLengthLow,
+  AwaitLengthHigh,
+  AwaitData,
+}
+
+#[derive(Debug)]
+pub struct Parser {
+  state: ParserState,
+  length: usize,
+  buffer: Vec<u8>,
+}
+
+impl Default for Parser {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Parser {
+  pub fn new() -> Self {
+    Parser {
+      state: ParserState::Idle,
+      length: 0,
+      buffer: Vec::new(),
+    }
+  }
+
+  fn reset(&mut self) {
+    self.state = ParserState::Idle;
+    self.length = 0;
+    self.buffer.clear();
+  }
+
+  pub fn parse_bytes(&mut self, input: Vec<u8>) -> ParserResult {
+    for value in input {
+      let result = self.parse(value);
+      if result != ParserResult::IncompleteFrame {
+        return result;
+      }
+    }
+    ParserResult::IncompleteFrame
+  }
+
+  pub fn parse(&mut self, value: u8) -> ParserResult {
+    match self.state {
+      ParserState::Idle => {
+        if value == DCH_START {
+          self.buffer.push(value);
+          self.state = ParserState::AwaitLengthLow;
+        }
+      },
+      ParserState::AwaitLengthLow => {
+        self.buffer.push(value);
+        self.length = value as usize;
+        self.state = ParserState::AwaitLengthHigh;
+      },
+      ParserState::AwaitLengthHigh => {
+        self.buffer.push(value);
+        self.length |= (value as usize) << 8;
+        if self.length < 2 + DCH_V2_HEADER_LENGTH + 1 + AFTER_DATA_LENGTH - 1 {
+          self.reset();
+          return ParserResult::InvalidFrame;
+        }
+        self.state = ParserState::AwaitData;
+      },
+      ParserState::AwaitData => {
+        self.buffer.push(value);
+        // The length excludes the start and end delimiters.
+        if self.buffer.len() == self.length + 2 {
+          let result = self.decode();
+          self.reset();
+          return result;
+        }
+      },
+    }
+    ParserResult::IncompleteFrame
+  }
+
+  pub fn timeout(&mut self) {
+    self.reset();
+  }
+
+  fn decode(&self) -> ParserResult {
+    let b = &self.buffer;
+    let end = b.len();
+    if b[end - 1] != DCH_END {
+      return ParserResult::InvalidFrame;
+    }
+
+    let version = u16::from_le_bytes([b[3], b[4]]);
+    let (header_length, timestamp_ms) = match version {
+      2 => {
+        let mut micros = [0u8; 8];
+        micros[..6].copy_from_slice(&b[5..11]);
+        (DCH_V2_HEADER_LENGTH, u64::from_le_bytes(micros) / 1_000)
+      },
+      3 => {
+        if end < 3 + DCH_V3_HEADER_LENGTH + 1 + AFTER_DATA_LENGTH {
+          return ParserResult::InvalidFrame;
+        }
+        let mut nanos = [0u8; 8];
+        nanos.copy_from_slice(&b[5..13]);
+        (DCH_V3_HEADER_LENGTH, u64::from_le_bytes(nanos) / 1_000_000)
+      },
+      _ => return ParserResult::InvalidFrame,
+    };
+
+    let data_start = 3 + header_length;
+    let data_end = end - AFTER_DATA_LENGTH;
+    if !matches!(b[data_start], PTI_RX_START | PTI_TX_START) || !matches!(b[data_end], PTI_RX_END | PTI_TX_END) {
+      return ParserResult::InvalidFrame;
+    }
+    if b[end - 3] != PROTOCOL_ZWAVE {
+      return ParserResult::InvalidFrame;
+    }
+
+    let region: Region = match PtiRegion::try_from(b[end - 5]) {
+      Ok(region) => region.into(),
+      Err(_) => return ParserResult::InvalidFrame,
+    };
+    let channel = b[end - 4];
+    let payload = b[data_start + 1..data_end].to_vec();
+    let frame_type = if payload.first() == Some(&BEAM_TAG) {
+      FrameType::Beam
+    } else {
+      FrameType::Data
+    };
+
+    ParserResult::ValidFrame {
+      frame: Frame {
+        frame_type,
+        region,
+        channel,
+        speed: speed_from_channel(region, channel),
+        timestamp: timestamp_ms as u16,
+        rssi: b[end - 6],
+        payload,
+      },
+    }
+  }
+}
+
+/// PTI frames carry no data rate, so derive it from the channel. Classic
+/// regions use channel 0 for 100 kbps, 1 for 40 kbps and 2 for 9.6 kbps,
+/// and Long Range uses channel 3 and up.
+fn speed_from_channel(region: Region, channel: u8) -> u8 {
+  match (region, channel) {
+    (Region::USLR | Region::EULR, 3..) => 3,
+    (_, 0) => 2,
+    (_, 1) => 1,
+    _ => 0,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser() {
+      let mut frame = Frame {
+        region: Region::US,
+        channel: 1,
+        speed: 1,
+        timestamp: 0,
+        rssi: 0xC5,
+        payload: vec![0xE5, 0x07, 0x76, 0x83, 0x01, 0x41, 0x0C, 0x0B, 0x02],
+        ..Default::default()
+      };
+      let pti = frame.to_pti_vector().unwrap();
+
+      let mut parser = Parser::new();
+      // Leading garbage is skipped.
+      assert_eq!(parser.parse_bytes(vec![0x00, 0x5D]), ParserResult::IncompleteFrame);
+      let result = parser.parse_bytes(pti.clone());
+      let ParserResult::ValidFrame { frame: decoded } = result else {
+        panic!("expected a valid frame, got {:?}", result);
+      };
+      assert_eq!(decoded.frame_type, FrameType::Data);
+      assert_eq!(decoded.region, frame.region);
+      assert_eq!(decoded.channel, frame.channel);
+      assert_eq!(decoded.speed, frame.speed);
+      assert_eq!(decoded.rssi, frame.rssi);
+      assert_eq!(decoded.payload, frame.payload);
+
+      // Wrong end delimiter.
+      let mut broken = pti.clone();
+      *broken.last_mut().unwrap() = 0x00;
+      assert_eq!(parser.parse_bytes(broken), ParserResult::InvalidFrame);
+
+      // Beam.
+      frame.payload = vec![0x55, 0x01, 0x02];
+      let result = parser.parse_bytes(frame.to_pti_vector().unwrap());
+      let ParserResult::ValidFrame { frame: beam } = result else {
+        panic!("expected a valid frame, got {:?}", result);
+      };
+      assert_eq!(beam.frame_type, FrameType::Beam);
+      assert_eq!(beam.beam_target(), Some(0x01));
+    }
+
+    #[test]
+    fn test_parser_v3() {
+      let mut pti: Vec<u8> = vec![0x5B, 0x00, 0x00, 0x03, 0x00];
+      pti.extend_from_slice(&5_000_000_000u64.to_le_bytes()); // 5 s in ns
+      pti.extend_from_slice(&[0x2A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x87]);
+      pti.push(PTI_RX_START);
+      pti.extend_from_slice(&[0xC0, 0xFF, 0xEE, 0x00, 0x01, 0x41, 0x03, 0x0D, 0x02, 0x00, 0x05]);
+      pti.extend_from_slice(&[PTI_RX_END, 0xB0, 12, 3, PROTOCOL_ZWAVE, 0x51, 0x5D]);
+      let length = (pti.len() - 2) as u16;
+      pti[1..3].copy_from_slice(&length.to_le_bytes());
+
+      let mut parser = Parser::new();
+      let result = parser.parse_bytes(pti);
+      let ParserResult::ValidFrame { frame } = result else {
+        panic!("expected a valid frame, got {:?}", result);
+      };
+      assert_eq!(frame.frame_type, FrameType::Data);
+      assert_eq!(frame.region, Region::USLR);
+      assert_eq!(frame.channel, 3);
+      assert_eq!(frame.speed, 3);
+      assert_eq!(frame.rssi, 0xB0);
+      assert_eq!(frame.timestamp, 5000);
+      assert_eq!(frame.payload.len(), 11);
+    }
+}
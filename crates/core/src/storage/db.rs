@@ -1,5 +1,6 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
+use tracing::error;
 use crate::storage::{
     DbFrame,
     FrameDatabase,
@@ -19,15 +20,24 @@ impl SqliteFrameDatabase {
             CREATE TABLE IF NOT EXISTS frames (
               id            INTEGER PRIMARY KEY,
               timestamp     INTEGER NOT NULL,
+              frame_type    INTEGER NOT NULL,
               speed         INTEGER,
               rssi          INTEGER,
               channel       INTEGER,
               home_id       INTEGER NOT NULL,
+              header_type   INTEGER NOT NULL,
               src_node_id   INTEGER,
               dst_node_id   INTEGER,
-              payload_raw   BLOB NOT NULL
+              payload_raw   BLOB NOT NULL,
+              region        INTEGER NOT NULL,
+              checksum_ok   INTEGER NOT NULL
             );
             CREATE INDEX idx_frames_timestamp ON frames (timestamp);
+            CREATE TABLE IF NOT EXISTS multicast_destinations (
+              frame_id      INTEGER NOT NULL REFERENCES frames (id),
+              node_id       INTEGER NOT NULL
+            );
+            CREATE INDEX idx_multicast_destinations_node_id ON multicast_destinations (node_id);
         ";
         connection.execute_batch(query).unwrap();
         SqliteFrameDatabase {
@@ -36,14 +46,22 @@ impl SqliteFrameDatabase {
     }
 }
 
+impl Default for SqliteFrameDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FrameDatabase for SqliteFrameDatabase {
     fn add_frame(&self, frame: DbFrame) {
         // Implementation to add a frame to the database
-        let query = "INSERT INTO frames (timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, payload_raw) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+        let query = "INSERT INTO frames (id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, payload_raw, frame_type, region, header_type, checksum_ok) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)";
 
         match self.connection.execute(
             query,
             rusqlite::params![
+                // NULL picks the next free ID.
+                (frame.id != 0).then_some(frame.id),
                 frame.timestamp,
                 frame.speed,
                 frame.rssi,
@@ -52,17 +70,34 @@ impl FrameDatabase for SqliteFrameDatabase {
                 frame.src_node_id,
                 frame.dst_node_id,
                 frame.payload,
+                frame.frame_type,
+                frame.region,
+                frame.header_type,
+                frame.checksum_ok,
             ],
         ) {
             Ok(_) => {
                 //println!("Frame added successfully");
             },
-            Err(e) => println!("Failed to add frame: {}", e),
+            Err(e) => {
+                error!(?e, "failed to add frame");
+                return;
+            },
+        }
+
+        let frame_id = self.connection.last_insert_rowid();
+        for node_id in &frame.multicast {
+            let query = "INSERT INTO multicast_destinations (frame_id, node_id) VALUES (?1, ?2)";
+            if let Err(e) = self.connection.execute(query, (frame_id, node_id)) {
+                error!(?e, node_id, "failed to add multicast destination");
+            }
         }
     }
 
     fn get_frame(&self, id: u64) -> Option<DbFrame> {
-        let query = "SELECT id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, payload_raw FROM frames WHERE id = (?1)";
+        let query = "SELECT id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, payload_raw, frame_type, region, header_type, checksum_ok, \
+            (SELECT group_concat(node_id) FROM multicast_destinations WHERE frame_id = frames.id) \
+            FROM frames WHERE id = (?1)";
 
         let mut statement = match self.connection.prepare(query) {
             Ok(stmt) => stmt,
@@ -72,7 +107,7 @@ impl FrameDatabase for SqliteFrameDatabase {
             },
         };
 
-        let frame_iter = match statement.query_map([id as i64], |row| {
+        let mut frame_iter = match statement.query_map([id as i64], |row| {
             Ok(DbFrame {
                 id: row.get(0)?,        // ID is selected in this query
                 timestamp: row.get(1)?, // Timestamp is selected in this query
@@ -82,7 +117,12 @@ impl FrameDatabase for SqliteFrameDatabase {
                 home_id: row.get(5)?,   // Home ID is selected in this query
                 src_node_id: row.get(6)?, // Source Node ID is selected in this query
                 dst_node_id: row.get(7)?, // Destination Node ID is selected in this query
-                payload: vec![],   // Payload is selected in this query
+                payload: row.get(8)?,   // Payload is selected in this query
+                frame_type: row.get(9)?,
+                region: row.get(10)?,
+                header_type: row.get(11)?,
+                checksum_ok: row.get(12)?,
+                multicast: parse_node_ids(row.get(13)?),
             })
         }) {
             Ok(iter) => iter,
@@ -92,24 +132,39 @@ impl FrameDatabase for SqliteFrameDatabase {
             },
         };
 
-        for frame in frame_iter {
-            match frame {
-                Ok(frame) => {
-                    //println!("Found frame with ID {}: {:?}", id, frame);
-                    return Some(frame);
-                },
-                Err(_e) => {
-                    //println!("Failed to parse frame: {}", e);
-                    return None
-                },
-            }
+        match frame_iter.next() {
+            Some(Ok(frame)) => {
+                //println!("Found frame with ID {}: {:?}", id, frame);
+                Some(frame)
+            },
+            Some(Err(_e)) => {
+                //println!("Failed to parse frame: {}", e);
+                None
+            },
+            None => None,
         }
-        None
     }
 
     fn get_frames(&self, offset: usize, limit: usize) -> Vec<DbFrame> {
-        let query = "SELECT id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id FROM frames LIMIT (?1) OFFSET (?2)";
+        let query = "SELECT id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, frame_type, region, header_type, checksum_ok, \
+            (SELECT group_concat(node_id) FROM multicast_destinations WHERE frame_id = frames.id) \
+            FROM frames LIMIT (?1) OFFSET (?2)";
+        self.query_frames(query, (limit as i64, offset as i64))
+    }
+
+    fn get_frames_to_node(&self, node_id: u16, offset: usize, limit: usize) -> Vec<DbFrame> {
+        let query = "SELECT id, timestamp, speed, rssi, channel, home_id, src_node_id, dst_node_id, frame_type, region, header_type, checksum_ok, \
+            (SELECT group_concat(node_id) FROM multicast_destinations WHERE frame_id = frames.id) \
+            FROM frames \
+            WHERE dst_node_id = (?1) OR id IN (SELECT frame_id FROM multicast_destinations WHERE node_id = (?1)) \
+            LIMIT (?2) OFFSET (?3)";
+        self.query_frames(query, (node_id, limit as i64, offset as i64))
+    }
+}
 
+impl SqliteFrameDatabase {
+    /// Run a query for frames without payload, as in `get_frames()`.
+    fn query_frames(&self, query: &str, params: impl rusqlite::Params) -> Vec<DbFrame> {
         let mut statement = match self.connection.prepare(query) {
             Ok(stmt) => stmt,
             Err(_e) => {
@@ -118,7 +173,7 @@ impl FrameDatabase for SqliteFrameDatabase {
             },
         };
 
-        let frame_iter = match statement.query_map((limit as i64, offset as i64), |row| {
+        let frame_iter = match statement.query_map(params, |row| {
             Ok(DbFrame {
                 id: row.get(0)?,        // ID is selected in this query
                 timestamp: row.get(1)?, // Timestamp is selected in this query
@@ -129,6 +184,11 @@ impl FrameDatabase for SqliteFrameDatabase {
                 src_node_id: row.get(6)?, // Source Node ID is selected in this query
                 dst_node_id: row.get(7)?, // Destination Node ID is selected in this query
                 payload: vec![], // Payload is not selected in this query
+                frame_type: row.get(8)?,
+                region: row.get(9)?,
+                header_type: row.get(10)?,
+                checksum_ok: row.get(11)?,
+                multicast: parse_node_ids(row.get(12)?),
             })
         }) {
             Ok(iter) => iter,
@@ -156,6 +216,16 @@ impl FrameDatabase for SqliteFrameDatabase {
     }
 }
 
+/// Parse the comma separated node IDs of `group_concat()`, NULL if there are
+/// none.
+fn parse_node_ids(node_ids: Option<String>) -> Vec<u16> {
+    node_ids
+        .iter()
+        .flat_map(|node_ids| node_ids.split(','))
+        .filter_map(|node_id| node_id.parse().ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,16 +235,52 @@ mod tests {
         let db = SqliteFrameDatabase::new();
         let frame = DbFrame {
             id: 0, // ID will be auto-generated by the database
+            frame_type: 1,
+            region: 0,
             timestamp: 1627849800,
             speed: 5,
             rssi: -100,
             channel: 60,
             home_id: 12345,
+            header_type: 1,
             src_node_id: 1,
-            dst_node_id: 2,
+            dst_node_id: 0x3E8, // Long Range node ID
             payload: vec![1, 2, 3, 4],
+            checksum_ok: false,
+            multicast: vec![],
         };
         db.add_frame(frame);
+        db.add_frame(DbFrame {
+            id: 0,
+            frame_type: 1,
+            region: 0,
+            timestamp: 1627849900,
+            speed: 2,
+            rssi: -90,
+            channel: 0,
+            home_id: 12345,
+            header_type: 2,
+            src_node_id: 1,
+            dst_node_id: 0,
+            payload: vec![],
+            checksum_ok: true,
+            multicast: vec![2, 0x3E8],
+        });
+
+        let stored = db.get_frame(1).unwrap();
+        assert_eq!(stored.header_type, 1);
+        assert_eq!(stored.dst_node_id, 0x3E8);
+        assert!(!stored.checksum_ok);
+        assert!(!db.get_frames(0, 10)[0].checksum_ok);
+        assert_eq!(db.get_frame(2).unwrap().multicast, vec![2, 0x3E8]);
+
+        // Both frames are addressed to node 0x3E8, one of them by multicast.
+        let frames = db.get_frames_to_node(0x3E8, 0, 10);
+        assert_eq!(frames.iter().map(|frame| frame.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(frames[1].is_addressed_to(2));
+        assert_eq!(db.get_frames_to_node(2, 0, 10).len(), 1);
+        assert!(db.get_frames_to_node(3, 0, 10).is_empty());
+        assert_eq!(stored.payload, vec![1, 2, 3, 4]);
         /*
         match db.query("SELECT id FROM frames") {
             Ok(result) => println!("Query successful: {}", result),
@@ -2,19 +2,38 @@
 // SPDX-License-Identifier: MIT
 #[derive(Debug)]
 pub struct DbFrame {
+  /// Frame ID, or 0 to store the frame with the next free ID.
   pub id: i64,
+  pub frame_type: u8,
+  pub region: u8,
   pub channel: u8,
   pub speed: u8,
   pub timestamp: i64,
   pub rssi: i8,
   pub home_id: u32,
-  pub src_node_id: u8,
-  pub dst_node_id: u8,
+  /// MAC header type, 0 if the MAC header was not decoded.
+  pub header_type: u8,
+  pub src_node_id: u16,
+  pub dst_node_id: u16,
   pub payload: Vec<u8>,
+  /// False if the checksum of a data frame does not match.
+  pub checksum_ok: bool,
+  /// Node IDs addressed by a multicast, empty for other frames.
+  pub multicast: Vec<u16>,
+}
+
+impl DbFrame {
+  /// Whether the frame is addressed to `node_id`, directly or by a multicast.
+  pub fn is_addressed_to(&self, node_id: u16) -> bool {
+    self.dst_node_id == node_id || self.multicast.contains(&node_id)
+  }
 }
 
 pub trait FrameDatabase {
     fn add_frame(&self, frame: DbFrame);
     fn get_frame(&self, id: u64) -> Option<DbFrame>;
     fn get_frames(&self, offset: usize, limit: usize) -> Vec<DbFrame>;
+    /// Like `get_frames()`, but only frames addressed to `node_id`, see
+    /// `DbFrame::is_addressed_to()`.
+    fn get_frames_to_node(&self, node_id: u16, offset: usize, limit: usize) -> Vec<DbFrame>;
 }
@@ -4,8 +4,12 @@ use std::fmt;
 use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 
+/// Zniffer speed value of Long Range frames.
+pub const SPEED_LONG_RANGE: u8 = 3;
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
+  pub frame_type: FrameType,
   pub region: Region,
   pub channel: u8,
   pub speed: u8,
@@ -14,6 +18,33 @@ pub struct Frame {
   pub payload: Vec<u8>,
 }
 
+/// Zniffer frame kinds (from Silicon Labs Zniffer API docs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum FrameType {
+    Command = 0x00,   // CMD_FRAME
+    #[default]
+    Data = 0x01,      // DATA_FRAME
+    Beam = 0x02,      // BEAM_FRAME
+    BeamStart = 0x04, // BEAM_START
+    BeamStop = 0x05,  // BEAM_STOP
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(FrameType::Command),
+            0x01 => Ok(FrameType::Data),
+            0x02 => Ok(FrameType::Beam),
+            0x04 => Ok(FrameType::BeamStart),
+            0x05 => Ok(FrameType::BeamStop),
+            _ => Err(()),
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
 pub enum Region {
@@ -138,6 +169,51 @@ impl From<Region> for PtiRegion {
     }
 }
 
+impl TryFrom<u8> for PtiRegion {
+    type Error = ParseRegionError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(PtiRegion::EU),
+            2 => Ok(PtiRegion::US),
+            3 => Ok(PtiRegion::ANZ),
+            4 => Ok(PtiRegion::HK),
+            5 => Ok(PtiRegion::IN),
+            9 => Ok(PtiRegion::IL),
+            8 => Ok(PtiRegion::RU),
+            11 => Ok(PtiRegion::CN),
+            12 => Ok(PtiRegion::USLR),
+            13 => Ok(PtiRegion::USLRBACK),
+            15 => Ok(PtiRegion::EULR),
+            7 => Ok(PtiRegion::JP),
+            10 => Ok(PtiRegion::KR),
+            14 => Ok(PtiRegion::USLRENDDEVICE),
+            _ => Err(ParseRegionError),
+        }
+    }
+}
+
+impl From<PtiRegion> for Region {
+    /// The US Long Range back-channel and end device codes only exist in PTI
+    /// traces and are reported as `Region::USLR`.
+    fn from(region: PtiRegion) -> Self {
+        match region {
+            PtiRegion::EU => Region::EU,
+            PtiRegion::US => Region::US,
+            PtiRegion::ANZ => Region::ANZ,
+            PtiRegion::HK => Region::HK,
+            PtiRegion::IN => Region::IN,
+            PtiRegion::IL => Region::IL,
+            PtiRegion::RU => Region::RU,
+            PtiRegion::CN => Region::CN,
+            PtiRegion::USLR | PtiRegion::USLRBACK | PtiRegion::USLRENDDEVICE => Region::USLR,
+            PtiRegion::EULR => Region::EULR,
+            PtiRegion::JP => Region::JP,
+            PtiRegion::KR => Region::KR,
+        }
+    }
+}
+
 //     SnifferPtiFrameClient.cs and PtiFrameParser.cs
 // if DCH version == 2 => dch length == 11 . if DCH version == 3 => dch length == 18
 // For simplicity this "PTI generator" will use version 2. therefore a frame would be:
@@ -153,6 +229,26 @@ impl From<Region> for PtiRegion {
 // ZWaveProtocol=0x06
 
 impl Frame {
+    /// Encode the frame as a Zniffer API data or beam frame, i.e., the
+    /// layout read by `zniffer_parser::Parser` and stored in ZLF Zniffer
+    /// records.
+    pub fn to_zniffer_vector(&self) -> Vec<u8> {
+      let mut buffer: Vec<u8> = Vec::with_capacity(10 + self.payload.len());
+      buffer.push(0x21); // SOF
+      buffer.push(self.frame_type as u8);
+      buffer.extend_from_slice(&self.timestamp.to_be_bytes());
+      buffer.push((self.channel << 5) | (self.speed & 0x1F));
+      buffer.push(self.region as u8);
+      buffer.push(self.rssi);
+      if self.frame_type == FrameType::Data {
+        buffer.push(0x21); // Start of data
+        buffer.push(0x03);
+      }
+      buffer.push(self.payload.len() as u8);
+      buffer.extend_from_slice(&self.payload);
+      buffer
+    }
+
     pub fn to_pti_vector(&mut self) -> Result<Vec<u8>, bool> {
 
       let mut buffer: Vec<u8> = vec![0; 15+self.payload.len()+7];
@@ -171,7 +267,7 @@ impl Frame {
       buffer[12] = 0x00;
       buffer[13] = 0x87;
       buffer[14] = 0xf8;
-      for i in 0..self.payload.len(){buffer[i+15] = self.payload[i] }
+      buffer[15..15 + self.payload.len()].copy_from_slice(&self.payload);
       let after_data_index: usize  = 14 + self.payload.len() + 1;
       buffer[after_data_index] = 0xF9;
       buffer[after_data_index+1] = self.rssi;
@@ -0,0 +1,191 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::zlf::types::{
+    ZlfProperties,
+    ZlfTimestamp,
+};
+
+const ZIP_LOCAL_HEADER: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+
+/// Attachment record (API type 0xF8) stored alongside the frames.
+///
+/// The PC Zniffer stores e.g. network keys, node names and session notes as
+/// attachments. `data` always holds the raw bytes so the record can be
+/// written back unchanged, while `content` is a best effort interpretation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub timestamp: ZlfTimestamp, // file timestamp
+    pub properties: ZlfProperties, // record properties byte
+    pub content: AttachmentContent,
+    pub data: Vec<u8>,   // raw attachment bytes
+}
+
+/// Interpretation of the attachment bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AttachmentContent {
+    /// XML document, with the name of its root element.
+    Xml { root: String, text: String },
+    /// Other UTF-8 text, e.g., session notes.
+    Text(String),
+    /// ZIP archive, with the entries found in its local file headers.
+    Zip(Vec<ZipEntry>),
+    /// Anything else.
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZipEntry {
+    pub name: String,
+    pub compressed_size: u32,
+    pub size: u32,
+}
+
+impl Attachment {
+    pub fn new(timestamp: ZlfTimestamp, properties: ZlfProperties, data: Vec<u8>) -> Self {
+        Attachment {
+            timestamp,
+            properties,
+            content: AttachmentContent::parse(&data),
+            data,
+        }
+    }
+
+    /// File extension matching the content, used when extracting attachments.
+    pub fn file_extension(&self) -> &'static str {
+        match self.content {
+            AttachmentContent::Xml { .. } => "xml",
+            AttachmentContent::Text(_) => "txt",
+            AttachmentContent::Zip(_) => "zip",
+            AttachmentContent::Binary => "bin",
+        }
+    }
+}
+
+impl AttachmentContent {
+    pub fn parse(data: &[u8]) -> Self {
+        if data.starts_with(&ZIP_LOCAL_HEADER) {
+            return AttachmentContent::Zip(parse_zip_entries(data));
+        }
+
+        // Strip a UTF-8 byte order mark, which .NET likes to write.
+        let text = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+        let text = match std::str::from_utf8(text) {
+            Ok(text) if !text.is_empty() && !text.contains('\0') => text,
+            _ => return AttachmentContent::Binary,
+        };
+
+        match xml_root(text) {
+            Some(root) => AttachmentContent::Xml { root, text: text.to_string() },
+            None => AttachmentContent::Text(text.to_string()),
+        }
+    }
+}
+
+/// Name of the root element if `text` looks like an XML document.
+fn xml_root(text: &str) -> Option<String> {
+    let mut rest = text.trim_start();
+    loop {
+        if let Some(r) = rest.strip_prefix("<?") {
+            rest = r.split_once("?>")?.1.trim_start();
+        } else if let Some(r) = rest.strip_prefix("<!--") {
+            rest = r.split_once("-->")?.1.trim_start();
+        } else if let Some(r) = rest.strip_prefix("<!") {
+            rest = r.split_once('>')?.1.trim_start();
+        } else {
+            break;
+        }
+    }
+    let rest = rest.strip_prefix('<')?;
+    let name: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect();
+    if name.is_empty() || !rest.contains('>') {
+        return None;
+    }
+    Some(name)
+}
+
+/// Walk the local file headers of a ZIP archive. Stops at the central
+/// directory or at an entry whose sizes are deferred to a data descriptor.
+fn parse_zip_entries(data: &[u8]) -> Vec<ZipEntry> {
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+    let mut entries = Vec::new();
+    let mut offset = 0usize;
+    while offset + 30 <= data.len() && data[offset..offset + 4] == ZIP_LOCAL_HEADER {
+        let flags = u16_at(offset + 6);
+        let compressed_size = u32_at(offset + 18);
+        let size = u32_at(offset + 22);
+        let name_length = u16_at(offset + 26) as usize;
+        let extra_length = u16_at(offset + 28) as usize;
+
+        let name_start = offset + 30;
+        let Some(name) = data.get(name_start..name_start + name_length) else {
+            break;
+        };
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            compressed_size,
+            size,
+        });
+
+        if flags & 0x08 != 0 {
+            break;
+        }
+        offset = name_start + name_length + extra_length + compressed_size as usize;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_content() {
+        let xml = b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<!-- keys -->\n<SecurityKeys><Key/></SecurityKeys>";
+        assert_eq!(
+            AttachmentContent::parse(xml),
+            AttachmentContent::Xml {
+                root: "SecurityKeys".to_string(),
+                text: "<?xml version=\"1.0\"?>\n<!-- keys -->\n<SecurityKeys><Key/></SecurityKeys>".to_string(),
+            },
+        );
+
+        assert_eq!(AttachmentContent::parse(b"node 5 is the lock"), AttachmentContent::Text("node 5 is the lock".to_string()));
+        assert_eq!(AttachmentContent::parse(b"a < b"), AttachmentContent::Text("a < b".to_string()));
+        assert_eq!(AttachmentContent::parse(&[0x00, 0xFF, 0x10]), AttachmentContent::Binary);
+        assert_eq!(AttachmentContent::parse(&[]), AttachmentContent::Binary);
+    }
+
+    #[test]
+    fn test_zip_entries() {
+        let mut zip = Vec::new();
+        for (name, content) in [("keys.xml", &b"<k/>"[..]), ("notes.txt", &b"hello"[..])] {
+            zip.extend_from_slice(&ZIP_LOCAL_HEADER);
+            zip.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            zip.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            zip.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&0u16.to_le_bytes());
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(content);
+        }
+        // Central directory signature ends the walk.
+        zip.extend_from_slice(&[0x50, 0x4B, 0x01, 0x02, 0, 0]);
+
+        let attachment = Attachment::new(ZlfTimestamp::default(), ZlfProperties::default(), zip.clone());
+        assert_eq!(attachment.file_extension(), "zip");
+        assert_eq!(attachment.data, zip);
+        assert_eq!(attachment.content, AttachmentContent::Zip(vec![
+            ZipEntry { name: "keys.xml".to_string(), compressed_size: 4, size: 4 },
+            ZipEntry { name: "notes.txt".to_string(), compressed_size: 5, size: 5 },
+        ]));
+    }
+}
@@ -0,0 +1,215 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::io::{
+    Read,
+    Seek,
+};
+use crate::types::FrameType;
+use crate::zlf::header::ZlfHeader;
+use crate::zlf::index::IndexEntry;
+use crate::zlf::reader::{
+    DataFrame,
+    RawFrame,
+    ZlfError,
+    ZlfReader,
+    ZlfRecord,
+};
+use crate::zlf::types::ApiType;
+use crate::pti_parser;
+use crate::zniffer_parser::{
+    self,
+    ParserResult,
+};
+
+/// Iterator over the decoded contents of a ZLF file.
+///
+/// Zniffer API bytes are fed through `zniffer_parser::Parser` and PTI records
+/// through `pti_parser::Parser`. Both keep their state across records, so
+/// frames split over several records are decoded too. Yields:
+/// - `ZlfRecord::Data` for every data and beam frame,
+/// - `ZlfRecord::Other` for Zniffer commands (`sof` = '#', `frame_type` =
+///   `Command`, `payload` = command ID, length and payload) and for records
+///   that cannot be decoded,
+/// - `ZlfRecord::Attachment` as-is.
+pub struct ZlfFrames<R: Read + Seek> {
+    reader: ZlfReader<R>,
+    parser: zniffer_parser::Parser,
+    pti_parser: pti_parser::Parser,
+    pending: VecDeque<ZlfRecord>,
+}
+
+impl<R: Read + Seek> ZlfFrames<R> {
+    pub fn new(reader: ZlfReader<R>) -> Self {
+        ZlfFrames {
+            reader,
+            parser: zniffer_parser::Parser::new(),
+            pti_parser: pti_parser::Parser::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn header(&self) -> &ZlfHeader {
+        self.reader.header()
+    }
+
+    /// Byte ranges skipped by a lenient reader, see `ZlfReader::lenient()`.
+    pub fn skipped(&self) -> &[Range<u64>] {
+        self.reader.skipped()
+    }
+
+    /// Number of Zniffer and PTI records read, see `ZlfReader::frame_count()`.
+    /// Frames come from the last record read when they are returned.
+    pub fn frame_count(&self) -> usize {
+        self.reader.frame_count()
+    }
+
+    /// Continue decoding at an indexed record, e.g., to fetch a frame by
+    /// number or time. Partially parsed frames are discarded.
+    pub fn seek_record(&mut self, entry: &IndexEntry) -> Result<(), ZlfError> {
+        self.reader.seek_record(entry)?;
+        self.parser = zniffer_parser::Parser::new();
+        self.pti_parser = pti_parser::Parser::new();
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn decode(&mut self, raw: RawFrame) {
+        match raw.api_type {
+            ApiType::Zniffer => self.decode_zniffer(raw),
+            ApiType::Pti => self.decode_pti(raw),
+            ApiType::Attachment => self.pending.push_back(ZlfRecord::Other(raw)),
+        }
+    }
+
+    fn decode_pti(&mut self, raw: RawFrame) {
+        for byte in raw.payload.iter() {
+            if let pti_parser::ParserResult::ValidFrame { frame } = self.pti_parser.parse(*byte) {
+                self.pending.push_back(ZlfRecord::Data(DataFrame {
+                    timestamp: raw.timestamp,
+                    properties: raw.properties,
+                    frame,
+                }));
+            }
+        }
+    }
+
+    fn decode_zniffer(&mut self, raw: RawFrame) {
+        for byte in raw.payload.iter() {
+            match self.parser.parse(*byte) {
+                ParserResult::ValidFrame { frame } => {
+                    self.pending.push_back(ZlfRecord::Data(DataFrame {
+                        timestamp: raw.timestamp,
+                        properties: raw.properties,
+                        frame,
+                    }));
+                },
+                ParserResult::ValidCommand { id, payload } => {
+                    let mut bytes = vec![id, payload.len() as u8];
+                    bytes.extend_from_slice(&payload);
+                    self.pending.push_back(ZlfRecord::Other(RawFrame {
+                        timestamp: raw.timestamp,
+                        properties: raw.properties,
+                        api_type: raw.api_type,
+                        sof: zniffer_parser::SOF_COMMAND,
+                        frame_type: FrameType::Command,
+                        payload: bytes,
+                    }));
+                },
+                ParserResult::IncompleteFrame | ParserResult::InvalidFrame => {
+                    // Continue parsing.
+                },
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Iterator for ZlfFrames<R> {
+    type Item = Result<ZlfRecord, ZlfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(Ok(record));
+            }
+            match self.reader.next() {
+                Ok(Some(ZlfRecord::Other(raw))) => self.decode(raw),
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> ZlfReader<R> {
+    /// Turn the reader into an iterator over decoded frames.
+    pub fn into_frames(self) -> ZlfFrames<R> {
+        ZlfFrames::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::types::{
+        Frame,
+        Region,
+    };
+    use crate::zlf::ZlfWriter;
+    use crate::zlf::types::ZlfTimestamp;
+
+    #[test]
+    fn test_frames() {
+        let frame: Vec<u8> = vec![
+            0x21, 0x01, 0x6D, 0xCE, 0x20, 0x00, 0x9D, 0x21, 0x03, 0x0A,
+            0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x81, 0x0D, 0x12, 0x20, 0x0B,
+        ];
+
+        let mut writer = ZlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        // Start command response followed by a frame split over two records.
+        writer.write_zniffer(ZlfTimestamp(1), &[0x23, 0x04, 0x00]).unwrap();
+        writer.write_zniffer(ZlfTimestamp(2), &frame[..7]).unwrap();
+        writer.write_attachment(ZlfTimestamp(3), b"notes").unwrap();
+        writer.write_zniffer(ZlfTimestamp(4), &frame[7..]).unwrap();
+        // Two frames in one record.
+        writer.write_zniffer(ZlfTimestamp(5), &[frame.clone(), frame].concat()).unwrap();
+        let mut pti_frame = Frame {
+            region: Region::US,
+            channel: 0,
+            payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x0C, 0x0B, 0x02],
+            ..Default::default()
+        }.to_pti_vector().unwrap();
+        writer.write_pti(ZlfTimestamp(6), &pti_frame).unwrap();
+        // Not a Z-Wave frame.
+        let protocol = pti_frame.len() - 3;
+        pti_frame[protocol] = 0x05;
+        writer.write_pti(ZlfTimestamp(7), &pti_frame).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let records: Vec<ZlfRecord> = ZlfReader::new(Cursor::new(bytes))
+            .unwrap()
+            .into_frames()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 6);
+
+        assert!(matches!(&records[0], ZlfRecord::Other(raw)
+            if raw.frame_type == FrameType::Command && raw.payload == [0x04, 0x00]));
+        assert!(matches!(&records[1], ZlfRecord::Attachment(_)));
+        for (record, timestamp) in records[2..5].iter().zip([4, 5, 5]) {
+            let ZlfRecord::Data(df) = record else {
+                panic!("expected a data frame, got {:?}", record);
+            };
+            assert_eq!(df.timestamp, ZlfTimestamp(timestamp));
+            assert_eq!(df.frame.frame_type, FrameType::Data);
+            assert_eq!(df.frame.region, Region::EU);
+            assert_eq!(df.frame.timestamp, 0x6DCE);
+            assert_eq!(df.frame.payload.len(), 10);
+        }
+        assert!(matches!(&records[5], ZlfRecord::Data(df)
+            if df.timestamp == ZlfTimestamp(6) && df.frame.region == Region::US && df.frame.speed == 2));
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::zlf::reader::ZlfError;
+use crate::zlf::types::{
+    ZLF_HEADER_SIZE,
+    ZLF_VERSION,
+};
+
+/// Text encoding (code page) of the header comment: UTF-8.
+pub const ENCODING_UTF8: u32 = 65001;
+/// Text encoding (code page) of the header comment: UTF-16 little endian.
+pub const ENCODING_UTF16: u32 = 1200;
+
+const COMMENT_OFFSET: usize = 8;
+const COMMENT_SIZE: usize = 512;
+const RESERVED_OFFSET: usize = COMMENT_OFFSET + COMMENT_SIZE;
+const CHECKSUM_OFFSET: usize = ZLF_HEADER_SIZE - 2;
+
+/// The 2048-byte ZLF file header.
+///
+/// Layout:
+/// - 0..4: version (u32 LE)
+/// - 4..8: text encoding of the comment (u32 LE code page)
+/// - 8..520: comment, NUL padded
+/// - 520..2046: reserved, kept as-is
+/// - 2046..2048: AUG_CCITT checksum of the preceding bytes (u16 LE)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZlfHeader {
+    pub version: u32,
+    pub encoding: u32,
+    pub comment: String,
+    pub reserved: Vec<u8>,
+}
+
+impl Default for ZlfHeader {
+    fn default() -> Self {
+        ZlfHeader {
+            version: ZLF_VERSION,
+            encoding: ENCODING_UTF8,
+            comment: String::new(),
+            reserved: vec![0u8; CHECKSUM_OFFSET - RESERVED_OFFSET],
+        }
+    }
+}
+
+impl ZlfHeader {
+    pub fn with_comment(comment: &str) -> Self {
+        ZlfHeader {
+            comment: comment.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Shorten the comment, on a character boundary, so that it fits the
+    /// header in its encoding.
+    pub fn truncate_comment(&mut self) {
+        let mut size = 0;
+        let end = self.comment.char_indices().find(|(_, c)| {
+            size += if self.encoding == ENCODING_UTF16 { 2 * c.len_utf16() } else { c.len_utf8() };
+            size > COMMENT_SIZE
+        });
+        if let Some((end, _)) = end {
+            self.comment.truncate(end);
+        }
+    }
+
+    /// Parse and validate a raw header.
+    pub fn from_bytes(header: &[u8; ZLF_HEADER_SIZE]) -> Result<Self, ZlfError> {
+        // Check header checksum
+        let file_checksum = u16::from_le_bytes([header[CHECKSUM_OFFSET], header[CHECKSUM_OFFSET + 1]]);
+        use crc16::*;
+        if State::<AUG_CCITT>::calculate(&header[..CHECKSUM_OFFSET]) != file_checksum {
+            return Err(ZlfError::InvalidStartPattern);
+        }
+
+        // Check for ZLF version at index 0.
+        let version: u32 = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        if version != ZLF_VERSION {
+            return Err(ZlfError::InvalidZlfVersion(version));
+        }
+
+        // Read 4 bytes / 32 bit of text encoding at index 4.
+        let encoding: u32 = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+        let comment = decode_text(encoding, &header[COMMENT_OFFSET..RESERVED_OFFSET]);
+
+        Ok(ZlfHeader {
+            version,
+            encoding,
+            comment,
+            reserved: header[RESERVED_OFFSET..CHECKSUM_OFFSET].to_vec(),
+        })
+    }
+
+    /// Serialize the header including its checksum.
+    pub fn to_bytes(&self) -> Result<[u8; ZLF_HEADER_SIZE], ZlfError> {
+        let mut header: [u8; ZLF_HEADER_SIZE] = [0u8; ZLF_HEADER_SIZE];
+        header[0..4].copy_from_slice(&self.version.to_le_bytes());
+        header[4..8].copy_from_slice(&self.encoding.to_le_bytes());
+
+        let comment = encode_text(self.encoding, &self.comment);
+        if comment.len() > COMMENT_SIZE {
+            return Err(ZlfError::CommentTooLong(comment.len()));
+        }
+        header[COMMENT_OFFSET..COMMENT_OFFSET + comment.len()].copy_from_slice(&comment);
+
+        let reserved_len = self.reserved.len().min(CHECKSUM_OFFSET - RESERVED_OFFSET);
+        header[RESERVED_OFFSET..RESERVED_OFFSET + reserved_len].copy_from_slice(&self.reserved[..reserved_len]);
+
+        // The last two bytes hold the checksum of everything before them.
+        use crc16::*;
+        let checksum = State::<AUG_CCITT>::calculate(&header[..CHECKSUM_OFFSET]);
+        header[CHECKSUM_OFFSET..].copy_from_slice(&checksum.to_le_bytes());
+        Ok(header)
+    }
+}
+
+/// Decode NUL padded text. Anything but UTF-16 is decoded as UTF-8, which
+/// also covers plain ASCII comments.
+fn decode_text(encoding: u32, bytes: &[u8]) -> String {
+    if encoding == ENCODING_UTF16 {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|u| *u != 0)
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    }
+}
+
+fn encode_text(encoding: u32, text: &str) -> Vec<u8> {
+    if encoding == ENCODING_UTF16 {
+        text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+    } else {
+        text.as_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let mut header = ZlfHeader::with_comment("TC-1234: inclusion of node 5");
+        header.reserved[0] = 0xAB;
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(ZlfHeader::from_bytes(&bytes).unwrap(), header);
+
+        let header = ZlfHeader { encoding: ENCODING_UTF16, ..ZlfHeader::with_comment("Smörgåsbord") };
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(&bytes[8..12], &[b'S', 0, b'm', 0]);
+        assert_eq!(ZlfHeader::from_bytes(&bytes).unwrap().comment, "Smörgåsbord");
+    }
+
+    #[test]
+    fn test_header_errors() {
+        let header = ZlfHeader::with_comment(&"x".repeat(COMMENT_SIZE + 1));
+        assert!(matches!(header.to_bytes(), Err(ZlfError::CommentTooLong(513))));
+
+        let mut bytes = ZlfHeader::default().to_bytes().unwrap();
+        bytes[100] ^= 0xFF;
+        assert!(matches!(ZlfHeader::from_bytes(&bytes), Err(ZlfError::InvalidStartPattern)));
+    }
+
+    #[test]
+    fn test_truncate_comment() {
+        let mut header = ZlfHeader::with_comment(&format!("{}åå", "x".repeat(COMMENT_SIZE - 3)));
+        header.truncate_comment();
+        assert_eq!(header.comment.len(), COMMENT_SIZE - 1);
+        assert!(header.to_bytes().is_ok());
+
+        let mut header = ZlfHeader { encoding: ENCODING_UTF16, ..ZlfHeader::with_comment(&"å".repeat(COMMENT_SIZE)) };
+        header.truncate_comment();
+        assert_eq!(header.comment.chars().count(), COMMENT_SIZE / 2);
+        assert!(header.to_bytes().is_ok());
+
+        let mut header = ZlfHeader::with_comment("short");
+        header.truncate_comment();
+        assert_eq!(header.comment, "short");
+    }
+}
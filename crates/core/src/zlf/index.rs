@@ -0,0 +1,351 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+use std::fs::File;
+use std::io::{
+    self,
+    BufReader,
+    BufWriter,
+    Read,
+    Seek,
+    SeekFrom,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use crate::zlf::header::ZlfHeader;
+use crate::zlf::reader::ZlfError;
+use crate::zlf::types::{
+    ApiType,
+    ZlfTimestamp,
+    RECORD_OVERHEAD,
+    ZLF_HEADER_SIZE,
+};
+
+const SIDECAR_MAGIC: [u8; 8] = *b"ZLFIDX\x01\x00";
+const SIDECAR_EXTENSION: &str = "idx";
+
+/// Offset, timestamp and API type of one sidecar entry.
+const SIDECAR_ENTRY_SIZE: usize = 17;
+
+/// Location of a single record in a ZLF file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: u64,             // byte offset of the record in the file
+    pub timestamp: ZlfTimestamp, // file timestamp
+    pub api_type: ApiType,
+}
+
+/// Byte offsets of the records in a ZLF file, for random access into traces
+/// too large to read sequentially.
+///
+/// Building the index only reads the record headers and skips the payloads.
+/// Frame numbers count the Zniffer and PTI records, like
+/// `ZlfReader::frame_count()`, while record numbers include attachments.
+///
+/// The index can be persisted as a sidecar file next to the trace, see
+/// [`ZlfIndex::persist`], and is then loaded by [`ZlfIndex::open`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ZlfIndex {
+    records: Vec<IndexEntry>,
+    frames: Vec<usize>,
+    end: u64,
+}
+
+impl ZlfIndex {
+    /// Validate the header of a ZLF file and index all of its records.
+    pub fn build<R: Read + Seek>(mut r: R) -> Result<Self, ZlfError> {
+        let mut header = [0u8; ZLF_HEADER_SIZE];
+        r.seek(SeekFrom::Start(0))?;
+        r.read_exact(&mut header)?;
+        ZlfHeader::from_bytes(&header)?;
+
+        let mut index = ZlfIndex {
+            end: ZLF_HEADER_SIZE as u64,
+            ..Default::default()
+        };
+        index.update(r)?;
+        Ok(index)
+    }
+
+    /// Index the records appended since the index was built, e.g., while a
+    /// capture is still being written. A truncated record at the end of the
+    /// file is left for the next update.
+    pub fn update<R: Read + Seek>(&mut self, mut r: R) -> Result<(), ZlfError> {
+        r.seek(SeekFrom::Start(self.end))?;
+        let mut r = BufReader::new(r);
+
+        loop {
+            // Timestamp, properties and payload length, without the API type.
+            let mut record_header = [0u8; RECORD_OVERHEAD as usize - 1];
+            if !read_complete(&mut r, &mut record_header)? {
+                return Ok(());
+            }
+            let timestamp = ZlfTimestamp(u64::from_le_bytes(record_header[0..8].try_into().unwrap()));
+            let payload_length = u32::from_le_bytes(record_header[9..13].try_into().unwrap());
+
+            r.seek_relative(payload_length as i64)?;
+            let mut api_type = [0u8; 1];
+            if !read_complete(&mut r, &mut api_type)? {
+                return Ok(());
+            }
+            let api_type = ApiType::try_from(api_type[0])
+                .map_err(|_| ZlfError::InvalidApiTypeField(api_type[0]))?;
+
+            if api_type != ApiType::Attachment {
+                self.frames.push(self.records.len());
+            }
+            self.records.push(IndexEntry { offset: self.end, timestamp, api_type });
+            self.end += RECORD_OVERHEAD + payload_length as u64;
+        }
+    }
+
+    /// Load the sidecar index of `path` if it is still valid, or index the
+    /// file. Records appended to the trace since the sidecar was written are
+    /// indexed as well. Nothing is written, see [`ZlfIndex::persist`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ZlfError> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        let loaded = File::open(Self::sidecar_path(path))
+            .ok()
+            .and_then(|f| Self::load(BufReader::new(f)).ok())
+            .filter(|index| index.matches(&mut file).unwrap_or(false));
+
+        match loaded {
+            Some(mut index) => {
+                index.update(&mut file)?;
+                Ok(index)
+            },
+            None => Self::build(&mut file),
+        }
+    }
+
+    /// Write the index to the sidecar file of the trace at `path`.
+    pub fn persist<P: AsRef<Path>>(&self, path: P) -> Result<(), ZlfError> {
+        let f = File::create(Self::sidecar_path(path))?;
+        self.save(BufWriter::new(f))
+    }
+
+    /// Path of the sidecar index of a trace, i.e., `trace.zlf.idx`.
+    pub fn sidecar_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut sidecar = path.as_ref().as_os_str().to_owned();
+        sidecar.push(".");
+        sidecar.push(SIDECAR_EXTENSION);
+        PathBuf::from(sidecar)
+    }
+
+    /// Read an index written by [`ZlfIndex::save`]. Fails with
+    /// `ZlfError::InvalidIndex` if `r` does not hold a complete index.
+    pub fn load<R: Read>(mut r: R) -> Result<Self, ZlfError> {
+        let mut magic = [0u8; 8];
+        read_index(&mut r, &mut magic)?;
+        if magic != SIDECAR_MAGIC {
+            return Err(ZlfError::InvalidIndex);
+        }
+        let mut value = [0u8; 8];
+        read_index(&mut r, &mut value)?;
+        let end = u64::from_le_bytes(value);
+        read_index(&mut r, &mut value)?;
+        let count = u64::from_le_bytes(value) as usize;
+
+        let mut index = ZlfIndex { end, ..Default::default() };
+        let mut entry = [0u8; SIDECAR_ENTRY_SIZE];
+        for _ in 0..count {
+            read_index(&mut r, &mut entry)?;
+            let api_type = ApiType::try_from(entry[16]).map_err(|_| ZlfError::InvalidIndex)?;
+            if api_type != ApiType::Attachment {
+                index.frames.push(index.records.len());
+            }
+            index.records.push(IndexEntry {
+                offset: u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                timestamp: ZlfTimestamp(u64::from_le_bytes(entry[8..16].try_into().unwrap())),
+                api_type,
+            });
+        }
+        Ok(index)
+    }
+
+    /// Write the index, e.g., to the sidecar file.
+    pub fn save<W: Write>(&self, mut w: W) -> Result<(), ZlfError> {
+        w.write_all(&SIDECAR_MAGIC)?;
+        w.write_all(&self.end.to_le_bytes())?;
+        w.write_all(&(self.records.len() as u64).to_le_bytes())?;
+        for entry in &self.records {
+            w.write_all(&entry.offset.to_le_bytes())?;
+            w.write_all(&entry.timestamp.0.to_le_bytes())?;
+            w.write_all(&[entry.api_type as u8])?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Check that a loaded index still describes `r`: the file must not have
+    /// shrunk and the last indexed record must still be in place.
+    fn matches<R: Read + Seek>(&self, r: &mut R) -> Result<bool, ZlfError> {
+        if r.seek(SeekFrom::End(0))? < self.end {
+            return Ok(false);
+        }
+        let Some(last) = self.records.last() else {
+            return Ok(self.end == ZLF_HEADER_SIZE as u64);
+        };
+        let mut timestamp = [0u8; 8];
+        r.seek(SeekFrom::Start(last.offset))?;
+        r.read_exact(&mut timestamp)?;
+        Ok(ZlfTimestamp(u64::from_le_bytes(timestamp)) == last.timestamp)
+    }
+
+    /// Number of records, including attachments.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Number of Zniffer and PTI records.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Byte offset just past the last indexed record.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    pub fn records(&self) -> &[IndexEntry] {
+        &self.records
+    }
+
+    pub fn record(&self, n: usize) -> Option<&IndexEntry> {
+        self.records.get(n)
+    }
+
+    /// Location of frame number `n`, counting from 0.
+    pub fn frame(&self, n: usize) -> Option<&IndexEntry> {
+        self.frames.get(n).map(|i| &self.records[*i])
+    }
+
+    /// Number of the first frame recorded at or after `timestamp`, or
+    /// `frame_count()` if there is none. Assumes the records are in
+    /// chronological order, as written by a capture.
+    pub fn seek_time(&self, timestamp: ZlfTimestamp) -> usize {
+        self.frames
+            .partition_point(|i| self.records[*i].timestamp.ticks() < timestamp.ticks())
+    }
+}
+
+/// Like `read_exact`, but returns `Ok(false)` if the input ends before the
+/// buffer is filled.
+fn read_complete<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<bool, ZlfError> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `read_exact`, failing with `ZlfError::InvalidIndex` if the index ends
+/// early.
+fn read_index<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), ZlfError> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(ZlfError::InvalidIndex),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::zlf::{
+        ZlfReader,
+        ZlfRecord,
+        ZlfWriter,
+    };
+
+    fn trace(frames: u64) -> Vec<u8> {
+        let mut writer = ZlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.write_attachment(ZlfTimestamp(5), b"notes").unwrap();
+        for i in 0..frames {
+            writer.write_zniffer(ZlfTimestamp(10 * (i + 1)), &vec![0x21; i as usize]).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_index() {
+        let bytes = trace(5);
+        let index = ZlfIndex::build(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(index.len(), 6);
+        assert_eq!(index.frame_count(), 5);
+        assert_eq!(index.end(), bytes.len() as u64);
+        assert_eq!(index.record(0).unwrap().api_type, ApiType::Attachment);
+
+        // Fetch frame 3 directly.
+        let mut reader = ZlfReader::new(Cursor::new(bytes.clone())).unwrap();
+        reader.seek_record(index.frame(3).unwrap()).unwrap();
+        let Some(ZlfRecord::Other(raw)) = reader.next().unwrap() else {
+            panic!("expected a raw frame");
+        };
+        assert_eq!(raw.timestamp, ZlfTimestamp(40));
+        assert_eq!(raw.payload.len(), 3);
+
+        assert_eq!(index.seek_time(ZlfTimestamp(0)), 0);
+        assert_eq!(index.seek_time(ZlfTimestamp(25)), 2);
+        assert_eq!(index.seek_time(ZlfTimestamp(30)), 2);
+        assert_eq!(index.seek_time(ZlfTimestamp(51)), 5);
+
+        // Sidecar round trip.
+        let mut sidecar = Vec::new();
+        index.save(&mut sidecar).unwrap();
+        assert_eq!(ZlfIndex::load(Cursor::new(sidecar.clone())).unwrap(), index);
+
+        // A damaged sidecar is told apart from a damaged trace.
+        assert!(matches!(ZlfIndex::load(Cursor::new(&sidecar[..sidecar.len() - 1])), Err(ZlfError::InvalidIndex)));
+        let mut broken = sidecar.clone();
+        broken[0] = b'X';
+        assert!(matches!(ZlfIndex::load(Cursor::new(broken)), Err(ZlfError::InvalidIndex)));
+        let mut broken = sidecar.clone();
+        *broken.last_mut().unwrap() = 0x00;
+        assert!(matches!(ZlfIndex::load(Cursor::new(broken)), Err(ZlfError::InvalidIndex)));
+    }
+
+    #[test]
+    fn test_persist() {
+        let path = std::env::temp_dir().join(format!("zniff-rs-{}-index.zlf", std::process::id()));
+        let sidecar = ZlfIndex::sidecar_path(&path);
+        std::fs::write(&path, trace(3)).unwrap();
+        let _ = std::fs::remove_file(&sidecar);
+
+        // Opening a trace leaves no sidecar behind.
+        let index = ZlfIndex::open(&path).unwrap();
+        assert!(!sidecar.exists());
+        index.persist(&path).unwrap();
+        assert_eq!(ZlfIndex::load(File::open(&sidecar).unwrap()).unwrap(), index);
+
+        // Records appended since are indexed on open.
+        std::fs::write(&path, trace(4)).unwrap();
+        assert_eq!(ZlfIndex::open(&path).unwrap().frame_count(), 4);
+
+        std::fs::remove_file(&sidecar).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_update() {
+        // A truncated record is picked up once it is complete.
+        let bytes = trace(4);
+        let mut index = ZlfIndex::build(Cursor::new(&bytes[..bytes.len() - 2])).unwrap();
+        assert_eq!(index.frame_count(), 3);
+        index.update(Cursor::new(&bytes)).unwrap();
+        assert_eq!(index, ZlfIndex::build(Cursor::new(&bytes)).unwrap());
+
+        let mut broken = bytes.clone();
+        *broken.last_mut().unwrap() = 0x00;
+        assert!(matches!(ZlfIndex::build(Cursor::new(broken)), Err(ZlfError::InvalidApiTypeField(0))));
+    }
+}
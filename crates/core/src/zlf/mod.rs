@@ -2,10 +2,36 @@
 // SPDX-License-Identifier: MIT
 pub mod types;
 //pub use types::ApiType;
+mod header;
+pub use header::{
+    ZlfHeader,
+    ENCODING_UTF16,
+    ENCODING_UTF8,
+};
+mod attachment;
+pub use attachment::{
+    Attachment,
+    AttachmentContent,
+    ZipEntry,
+};
 mod reader;
+pub use crate::types::FrameType;
 pub use reader::{
+    DataFrame,
+    RawFrame,
+    ZlfError,
     ZlfRecord,
     ZlfReader,
 };
 
-//mod writer; // not yet implemented
+mod frames;
+pub use frames::ZlfFrames;
+
+mod writer;
+pub use writer::ZlfWriter;
+
+mod index;
+pub use index::{
+    IndexEntry,
+    ZlfIndex,
+};
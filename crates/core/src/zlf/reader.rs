@@ -4,71 +4,68 @@ use std::io::{
     self,
     Read,
     Seek,
+    SeekFrom,
 };
+use std::ops::Range;
 use serde::{
     Deserialize,
     Serialize,
 };
 use thiserror::Error;
+use crate::types::{
+    Frame,
+    FrameType,
+};
+use crate::zlf::attachment::Attachment;
+use crate::zlf::header::ZlfHeader;
+use crate::zlf::index::IndexEntry;
 use crate::zlf::types::{
     ApiType,
-    ZLF_VERSION,
+    ZlfProperties,
+    ZlfTimestamp,
+    RECORD_OVERHEAD,
+    ZLF_HEADER_SIZE,
 };
 
-/// Zniffer frame kinds (from Silicon Labs Zniffer API docs).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[repr(u8)]
-pub enum FrameType {
-    Command = 0x00,   // CMD_FRAME
-    Data = 0x01,      // DATA_FRAME
-    Beam = 0x02,      // BEAM_FRAME
-    BeamStart = 0x04, // BEAM_START
-    BeamStop = 0x05,  // BEAM_STOP
-}
-
-impl TryFrom<u8> for FrameType {
-    type Error = ();
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0x00 => Ok(FrameType::Command),
-            0x01 => Ok(FrameType::Data),
-            0x02 => Ok(FrameType::Beam),
-            0x04 => Ok(FrameType::BeamStart),
-            0x05 => Ok(FrameType::BeamStop),
-            _ => Err(()),
-        }
-    }
-}
-
 /// Raw frame as read from ZLF after header.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawFrame {
-    pub timestamp: u64, // file timestamp
+    pub timestamp: ZlfTimestamp, // file timestamp
+    pub properties: ZlfProperties, // record properties byte
+    pub api_type: ApiType, // Zniffer or PTI
     pub sof: u8,         // SOF '#' or SODF '!'
     pub frame_type: FrameType,  // parsed type
     pub payload: Vec<u8> // raw payload bytes
 }
 
-/// Decoded DATA_FRAME fields (payload layout mirrors device->host Zniffer API).
-/// payload := [ts_lo, ts_hi, ch_speed, region, rssi (i8), mpdu_len, mpdu...]
-#[derive(Debug, Clone)]
+/// Frame decoded from the Zniffer API bytes of one or more ZLF records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataFrame {
-    pub timestamp: u16,       // wraps; device ticks
-    pub ch_and_speed: u8,     // channel & bitrate
-    pub region: u8,           // region code
-    pub rssi: i8,             // signed RSSI
-    pub mpdu: Vec<u8>,        // Z-Wave MPDU bytes
+    pub timestamp: ZlfTimestamp, // file timestamp of the record completing the frame
+    pub properties: ZlfProperties, // record properties byte
+    pub frame: Frame,          // decoded data or beam frame incl. the payload
 }
 
-/// Either a decoded DATA_FRAME or a raw frame for other types.
+/// A ZLF record. `ZlfReader` yields attachments and raw frames, while
+/// `ZlfFrames` decodes the raw frames into data frames and commands.
 #[derive(Debug, Clone)]
 pub enum ZlfRecord {
-    Attachment,
+    Attachment(Attachment),
     Data(DataFrame),
     Other(RawFrame),
 }
 
+impl ZlfRecord {
+    /// File timestamp of the record.
+    pub fn timestamp(&self) -> ZlfTimestamp {
+        match self {
+            ZlfRecord::Attachment(attachment) => attachment.timestamp,
+            ZlfRecord::Data(df) => df.timestamp,
+            ZlfRecord::Other(raw) => raw.timestamp,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ZlfError {
     #[error("Invalid ZLF version: {0}")]
@@ -87,46 +84,73 @@ pub enum ZlfError {
     BadMarker(u8),
     #[error("payload too short for data frame")]
     ShortDataPayload,
+    #[error("comment too long for the ZLF header: {0} bytes")]
+    CommentTooLong(usize),
+    #[error("record type cannot be written to a ZLF file")]
+    UnsupportedRecord,
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("invalid or truncated index file")]
+    InvalidIndex,
 }
 
 pub struct ZlfReader<R: Read + Seek> {
     r: R,
-    // If you discover version markers in the 2048-byte header, store them here
-    // to adjust parsing per version.
+    header: ZlfHeader,
     frame_counter: usize,
+    position: u64,
+    lenient: bool,
+    skipped: Vec<Range<u64>>,
 }
 
 impl<R: Read + Seek> ZlfReader<R> {
     pub fn new(mut r: R) -> Result<Self, ZlfError> {
         // Read the 2048-byte header into a buffer.
-        let mut header: [u8; 2048] = [0u8; 2048];
+        let mut header: [u8; ZLF_HEADER_SIZE] = [0u8; ZLF_HEADER_SIZE];
         r.read_exact(&mut header)?;
+        let header = ZlfHeader::from_bytes(&header)?;
 
-        // Check header checksum
-        let file_checksum = u16::from_le_bytes([header[2046], header[2047]]);
-        use crc16::*;
-        if State::<AUG_CCITT>::calculate(&header[..2046]) != file_checksum {
-            return Err(ZlfError::InvalidStartPattern);
-        }
-
-        // Check for ZLF version at index 0.
-        let version: u32 = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
-        if version != ZLF_VERSION {
-            return Err(ZlfError::InvalidZlfVersion(version));
-        }
-
-        // Read 4 bytes / 32 bit of text encoding at index 4.
-        let _encoding: u32 = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        Ok(Self {
+            r,
+            header,
+            frame_counter: 0,
+            position: ZLF_HEADER_SIZE as u64,
+            lenient: false,
+            skipped: Vec::new(),
+        })
+    }
 
-        // TODO: Read comment at index 8. 512 bytes.
+    /// Recover from truncated or corrupted records instead of failing.
+    ///
+    /// In lenient mode, a record that is cut off or has an invalid API type
+    /// is skipped and reading resumes at the next plausible record boundary.
+    /// The skipped byte ranges are reported by `skipped()`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
 
-        Ok(Self { r, frame_counter: 0 })
+    pub fn header(&self) -> &ZlfHeader {
+        &self.header
     }
 
     pub fn frame_count(&self) -> usize {
         self.frame_counter
     }
 
+    /// Byte ranges skipped in lenient mode, in file order.
+    pub fn skipped(&self) -> &[Range<u64>] {
+        &self.skipped
+    }
+
+    /// Position the reader at an indexed record, so the next call to `next()`
+    /// returns it.
+    pub fn seek_record(&mut self, entry: &IndexEntry) -> Result<(), ZlfError> {
+        self.r.seek(SeekFrom::Start(entry.offset))?;
+        self.position = entry.offset;
+        Ok(())
+    }
+
     /// Read 2048 bytes and invoke a callback for each frame. Returns Ok(()) at EOF.
     pub fn read_frames<F>(&mut self, mut callback: F) -> Result<(), ZlfError>
     where
@@ -139,34 +163,45 @@ impl<R: Read + Seek> ZlfReader<R> {
     }
 
     /// Read the next frame. Returns Ok(None) at EOF.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<ZlfRecord>, ZlfError> {
+        loop {
+            match self.read_record() {
+                Err(ZlfError::Eof | ZlfError::InvalidApiTypeField(_)) if self.lenient => {
+                    let start = self.position;
+                    self.position = self.resync(start + 1)?;
+                    self.skipped.push(start..self.position);
+                    self.r.seek(SeekFrom::Start(self.position))?;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<ZlfRecord>, ZlfError> {
         // Read a timestamp of 8 bytes
         let mut timestamp = [0u8; 8];
-        match self.r.read_exact(&mut timestamp) {
-            Ok(()) => {},
-            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
-            Err(e) => return Err(e.into()),
+        match self.r.read(&mut timestamp)? {
+            0 => return Ok(None),
+            n => read_remaining(&mut self.r, &mut timestamp[n..])?,
         }
 
         let mut properties = [0u8; 1];
-        self.r.read_exact(&mut properties)?;
+        read_remaining(&mut self.r, &mut properties)?;
 //        if properties[0] != 0 && properties[0] != 0x81 {
 //            return Err(ZlfError::InvalidPropertiesField(properties[0]));
 //        }
 
         let mut payload_length = [0u8; 4];
-        self.r.read_exact(&mut payload_length)?;
+        read_remaining(&mut self.r, &mut payload_length)?;
         let payload_length: u32 = u32::from_le_bytes(payload_length);
         //println!("Payload length: {:?}", payload_length);
 
-        let mut payload: Vec<u8> = vec![0u8; payload_length as usize];
-        let mut read = 0usize;
-        while read < payload_length as usize {
-            let n = self.r.read(&mut payload[read..])?;
-            if n == 0 {
-                return Err(ZlfError::Eof);
-            }
-            read += n;
+        // Don't trust a corrupted length with a huge allocation.
+        let mut payload: Vec<u8> = Vec::new();
+        let read = (&mut self.r).take(payload_length as u64).read_to_end(&mut payload)?;
+        if read < payload_length as usize {
+            return Err(ZlfError::Eof);
         }
 
         //for byte in &payload {
@@ -175,16 +210,149 @@ impl<R: Read + Seek> ZlfReader<R> {
         //println!();
 
         let mut api_type = [0u8; 1];
-        self.r.read_exact(&mut api_type)?;
-        match ApiType::try_from(api_type[0]) {
-            Ok(ApiType::Attachment) => { return Ok(Some(ZlfRecord::Attachment)); },
-            Ok(ApiType::Pti) | Ok(ApiType::Zniffer) => {
+        read_remaining(&mut self.r, &mut api_type)?;
+        let api_type = ApiType::try_from(api_type[0])
+            .map_err(|_| ZlfError::InvalidApiTypeField(api_type[0]))?;
+        self.position += RECORD_OVERHEAD + payload_length as u64;
+
+        let timestamp = ZlfTimestamp(u64::from_le_bytes(timestamp));
+        let properties = ZlfProperties(properties[0]);
+        match api_type {
+            ApiType::Attachment => {
+                Ok(Some(ZlfRecord::Attachment(Attachment::new(timestamp, properties, payload))))
+            },
+            ApiType::Pti | ApiType::Zniffer => {
                 self.frame_counter += 1;
                 // TODO: Do we need the frame type?
                 let frame_type = FrameType::Data;
-                Ok(Some(ZlfRecord::Other(RawFrame { timestamp: 0, sof: 0, frame_type, payload })))
+                Ok(Some(ZlfRecord::Other(RawFrame { timestamp, properties, api_type, sof: 0, frame_type, payload })))
             },
-            Err(_) => { return Err(ZlfError::InvalidApiTypeField(api_type[0])); },
         }
     }
+
+    /// Offset of the first plausible record at or after `from`, or the end
+    /// of the file if there is none.
+    ///
+    /// A record is plausible if its timestamp is within this century, its
+    /// payload fits in the file, it ends with a valid API type and it is
+    /// followed by the end of the file or by another plausible timestamp.
+    fn resync(&mut self, from: u64) -> Result<u64, ZlfError> {
+        let end = self.r.seek(SeekFrom::End(0))?;
+        let mut window = vec![0u8; RESYNC_WINDOW];
+        let mut offset = from;
+
+        while offset + RECORD_OVERHEAD <= end {
+            self.r.seek(SeekFrom::Start(offset))?;
+            let length = (&mut self.r).take(RESYNC_WINDOW as u64).read(&mut window)?;
+            for i in 0..length.saturating_sub(7) {
+                let candidate = offset + i as u64;
+                if plausible_timestamp(&window[i..i + 8]) && self.plausible_record(candidate, end)? {
+                    return Ok(candidate);
+                }
+            }
+            offset += length.saturating_sub(7).max(1) as u64;
+        }
+        Ok(end)
+    }
+
+    fn plausible_record(&mut self, offset: u64, end: u64) -> Result<bool, ZlfError> {
+        let mut header = [0u8; 13];
+        self.r.seek(SeekFrom::Start(offset))?;
+        if read_remaining(&mut self.r, &mut header).is_err() {
+            return Ok(false);
+        }
+        let next = offset + RECORD_OVERHEAD + u32::from_le_bytes([header[9], header[10], header[11], header[12]]) as u64;
+        if next > end {
+            return Ok(false);
+        }
+
+        let mut api_type = [0u8; 1];
+        self.r.seek(SeekFrom::Start(next - 1))?;
+        self.r.read_exact(&mut api_type)?;
+        if ApiType::try_from(api_type[0]).is_err() {
+            return Ok(false);
+        }
+        if next == end {
+            return Ok(true);
+        }
+
+        let mut timestamp = [0u8; 8];
+        Ok(read_remaining(&mut self.r, &mut timestamp).is_ok() && plausible_timestamp(&timestamp))
+    }
+}
+
+/// Bytes scanned at a time while looking for the next record.
+const RESYNC_WINDOW: usize = 64 * 1024;
+
+/// Whether 8 bytes look like a record timestamp between 2000 and 2100.
+fn plausible_timestamp(bytes: &[u8]) -> bool {
+    let timestamp = ZlfTimestamp(u64::from_le_bytes(bytes[..8].try_into().unwrap()));
+    (946_684_800_000_000..4_102_444_800_000_000).contains(&timestamp.unix_micros())
+}
+
+/// `read_exact`, failing with `ZlfError::Eof` if the input ends early.
+fn read_remaining<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), ZlfError> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(ZlfError::Eof),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::zlf::ZlfWriter;
+
+    const T0: u64 = 0x48DD_1C5A_7B2E_0000;
+
+    fn trace() -> Vec<u8> {
+        let mut writer = ZlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        for i in 0..4u8 {
+            writer.write_zniffer(ZlfTimestamp(T0 + i as u64), &[0x21, i, i]).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn payloads(reader: &mut ZlfReader<Cursor<Vec<u8>>>) -> Result<Vec<u8>, ZlfError> {
+        let mut payloads = Vec::new();
+        while let Some(record) = reader.next()? {
+            let ZlfRecord::Other(raw) = record else {
+                panic!("expected a raw frame, got {:?}", record);
+            };
+            payloads.push(raw.payload[1]);
+        }
+        Ok(payloads)
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut bytes = trace();
+        bytes.truncate(bytes.len() - 5);
+
+        let mut reader = ZlfReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert!(matches!(payloads(&mut reader), Err(ZlfError::Eof)));
+
+        let mut reader = ZlfReader::new(Cursor::new(bytes.clone())).unwrap().lenient(true);
+        assert_eq!(payloads(&mut reader).unwrap(), [0, 1, 2]);
+        let record_size = 3 + RECORD_OVERHEAD;
+        let start = ZLF_HEADER_SIZE as u64 + 3 * record_size;
+        assert_eq!(reader.skipped(), &[Range { start, end: bytes.len() as u64 }]);
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let mut bytes = trace();
+        let record_size = 3 + RECORD_OVERHEAD as usize;
+        // Corrupt the length and the API type of the second record.
+        let second = ZLF_HEADER_SIZE + record_size;
+        bytes[second + 9] = 0x40;
+        bytes[second + record_size - 1] = 0x00;
+
+        let mut reader = ZlfReader::new(Cursor::new(bytes.clone())).unwrap().lenient(true);
+        assert_eq!(payloads(&mut reader).unwrap(), [0, 2, 3]);
+        assert_eq!(reader.skipped(), &[Range { start: second as u64, end: (second + record_size) as u64 }]);
+        assert_eq!(reader.frame_count(), 3);
+    }
 }
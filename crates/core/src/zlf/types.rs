@@ -1,8 +1,28 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
+use std::fmt;
+use std::str::FromStr;
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::zlf::reader::ZlfError;
+
 pub const ZLF_VERSION: u32 = 104;
 
+/// Size of the fixed file header preceding the first record.
+pub const ZLF_HEADER_SIZE: usize = 2048;
+
+/// Timestamp, properties, payload length and API type around the payload.
+pub const RECORD_OVERHEAD: u64 = 14;
+
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ApiType {
     Pti = 0xF5,
     Attachment = 0xF8,
@@ -21,3 +41,207 @@ impl TryFrom<u8> for ApiType {
         }
     }
 }
+
+/// Number of .NET `DateTime` ticks (100 ns) between 0001-01-01 and the Unix epoch.
+const UNIX_EPOCH_TICKS: u64 = 621_355_968_000_000_000;
+
+/// `DateTimeKind.Utc` marker in the top bits of a `DateTime.ToBinary()` value.
+const DATETIME_KIND_UTC: u64 = 0x4000_0000_0000_0000;
+
+/// The `DateTimeKind` bits of a `DateTime.ToBinary()` value.
+const DATETIME_KIND_MASK: u64 = 0xC000_0000_0000_0000;
+
+/// Record timestamp as stored by the PC Zniffer, i.e., a .NET
+/// `DateTime.ToBinary()` value. Both UTC and local kinds store UTC ticks, so
+/// the kind bits are ignored when decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ZlfTimestamp(pub u64);
+
+impl ZlfTimestamp {
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        ZlfTimestamp((UNIX_EPOCH_TICKS + (since_epoch.as_nanos() / 100) as u64) | DATETIME_KIND_UTC)
+    }
+
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    pub fn from_unix_micros(micros: i64) -> Self {
+        let ticks = (UNIX_EPOCH_TICKS as i64 + micros * 10) as u64;
+        ZlfTimestamp(ticks | DATETIME_KIND_UTC)
+    }
+
+    /// Ticks (100 ns) since 0001-01-01 UTC.
+    pub fn ticks(&self) -> u64 {
+        self.0 & !DATETIME_KIND_MASK
+    }
+
+    /// Microseconds since the Unix epoch. Negative for timestamps before 1970.
+    pub fn unix_micros(&self) -> i64 {
+        (self.ticks() as i64 - UNIX_EPOCH_TICKS as i64) / 10
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        let micros = self.unix_micros();
+        if micros >= 0 {
+            UNIX_EPOCH + Duration::from_micros(micros as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_micros(micros.unsigned_abs())
+        }
+    }
+
+    /// Time of day in UTC, formatted as `HH:MM:SS.ffffff`.
+    pub fn format_time(&self) -> String {
+        let (_, _, _, time) = self.civil();
+        time
+    }
+
+    /// Split into (year, month, day, "HH:MM:SS.ffffff") in UTC.
+    fn civil(&self) -> (i64, u32, u32, String) {
+        let micros = self.unix_micros();
+        let days = micros.div_euclid(86_400_000_000);
+        let micros_of_day = micros.rem_euclid(86_400_000_000);
+
+        // Days since the Unix epoch to a proleptic Gregorian date, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        let seconds = micros_of_day / 1_000_000;
+        let time = format!(
+            "{:02}:{:02}:{:02}.{:06}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60,
+            micros_of_day % 1_000_000,
+        );
+        (year, month, day, time)
+    }
+}
+
+impl FromStr for ZlfTimestamp {
+    type Err = ZlfError;
+
+    /// Parses `YYYY-MM-DDTHH:MM:SS[.ffffff][Z]` in UTC, i.e., the format
+    /// written by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ZlfError::InvalidTimestamp(s.to_string());
+        let (date, time) = s.split_once(['T', ' ']).ok_or_else(invalid)?;
+        let time = time.strip_suffix('Z').unwrap_or(time);
+
+        let number = |field: &str, digits: usize| -> Result<i64, ZlfError> {
+            if field.len() != digits || !field.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            field.parse().map_err(|_| invalid())
+        };
+
+        let mut date = date.split('-');
+        let (Some(year), Some(month), Some(day), None) = (date.next(), date.next(), date.next(), date.next()) else {
+            return Err(invalid());
+        };
+        let (year, month, day) = (number(year, 4)?, number(month, 2)?, number(day, 2)?);
+
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let mut time = time.split(':');
+        let (Some(hour), Some(minute), Some(second), None) = (time.next(), time.next(), time.next(), time.next()) else {
+            return Err(invalid());
+        };
+        let (hour, minute, second) = (number(hour, 2)?, number(minute, 2)?, number(second, 2)?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+            return Err(invalid());
+        }
+
+        // Fractional seconds, truncated to microseconds.
+        let mut micros = 0;
+        if !fraction.is_empty() {
+            let digits = &fraction[..fraction.len().min(6)];
+            micros = number(digits, digits.len())? * 10i64.pow(6 - digits.len() as u32);
+            number(fraction, fraction.len())?;
+        }
+
+        // Proleptic Gregorian date to days since the Unix epoch, see
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+
+        let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+        Ok(ZlfTimestamp::from_unix_micros(seconds * 1_000_000 + micros))
+    }
+}
+
+impl fmt::Display for ZlfTimestamp {
+    /// Formats as `YYYY-MM-DDTHH:MM:SS.ffffffZ` (RFC 3339, UTC).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day, time) = self.civil();
+        write!(f, "{:04}-{:02}-{:02}T{}Z", year, month, day, time)
+    }
+}
+
+/// Record properties byte. Bit 7 is the direction flag, set for frames the
+/// PC Zniffer transmitted itself. The remaining bits hold the session ID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ZlfProperties(pub u8);
+
+impl ZlfProperties {
+    pub fn is_outgoing(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+
+    pub fn session_id(&self) -> u8 {
+        self.0 & 0x7F
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        // 2024-02-29T12:34:56.789012Z as written by `DateTime.ToBinary()` for a UTC time.
+        let timestamp = ZlfTimestamp(0x08DC_3922_D65D_7CC8 | DATETIME_KIND_UTC);
+        assert_eq!(timestamp.unix_micros(), 1_709_210_096_789_012);
+        assert_eq!(timestamp.to_string(), "2024-02-29T12:34:56.789012Z");
+        assert_eq!(timestamp.format_time(), "12:34:56.789012");
+
+        // Local kind stores UTC ticks too.
+        let local = ZlfTimestamp(timestamp.ticks() | 0x8000_0000_0000_0000);
+        assert_eq!(local.to_system_time(), timestamp.to_system_time());
+
+        let system_time = UNIX_EPOCH + Duration::from_micros(1_709_210_096_789_012);
+        assert_eq!(ZlfTimestamp::from_system_time(system_time), timestamp);
+        assert_eq!(ZlfTimestamp::from_unix_micros(1_709_210_096_789_012), timestamp);
+
+        assert_eq!("2024-02-29T12:34:56.789012Z".parse::<ZlfTimestamp>().unwrap(), timestamp);
+        assert_eq!(
+            "2024-02-29 12:34:56.7".parse::<ZlfTimestamp>().unwrap().unix_micros(),
+            1_709_210_096_700_000,
+        );
+        assert_eq!("1970-01-01T00:00:00".parse::<ZlfTimestamp>().unwrap().unix_micros(), 0);
+        for invalid in ["2024-02-29", "2024-13-01T00:00:00Z", "2024-02-29T12:34", "2024-02-29T12:34:56.x"] {
+            assert!(invalid.parse::<ZlfTimestamp>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_properties() {
+        let properties = ZlfProperties(0x81);
+        assert!(properties.is_outgoing());
+        assert_eq!(properties.session_id(), 1);
+        assert!(!ZlfProperties(0x00).is_outgoing());
+    }
+}
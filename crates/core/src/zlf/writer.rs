@@ -1,17 +1,144 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
 use std::io::{Seek, Write};
-use crate::zlf::types::ZLF_VERSION;
-use crate::zlf::reader::ZlfError;
+use crate::types::FrameType;
+use crate::zlf::header::ZlfHeader;
+use crate::zlf::types::{
+    ApiType,
+    ZlfProperties,
+    ZlfTimestamp,
+};
+use crate::zlf::reader::{
+    RawFrame,
+    ZlfError,
+    ZlfRecord,
+};
 
 pub struct ZlfWriter<W: Write + Seek> {
     w: W,
+    frame_counter: usize,
 }
 
 impl<W: Write + Seek> ZlfWriter<W> {
-    /// Construct writer and write the static 2048-byte header.
-    pub fn new(mut w: W) -> Result<Self, ZlfError> {
-        w.write_all(&[ZLF_VERSION])?;
-        Ok(ZlfWriter { w })
+    /// Construct writer and write a default 2048-byte header.
+    pub fn new(w: W) -> Result<Self, ZlfError> {
+        Self::with_header(w, &ZlfHeader::default())
+    }
+
+    /// Construct writer and write the given header, e.g., with a comment or
+    /// copied from a `ZlfReader`.
+    pub fn with_header(mut w: W, header: &ZlfHeader) -> Result<Self, ZlfError> {
+        w.write_all(&header.to_bytes()?)?;
+        Ok(ZlfWriter { w, frame_counter: 0 })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_counter
+    }
+
+    /// Write a single record with the given file timestamp, properties and API type.
+    pub fn write_raw(&mut self, timestamp: ZlfTimestamp, properties: ZlfProperties, api_type: ApiType, payload: &[u8]) -> Result<(), ZlfError> {
+        self.w.write_all(&timestamp.0.to_le_bytes())?;
+        self.w.write_all(&[properties.0])?;
+        self.w.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.w.write_all(payload)?;
+        self.w.write_all(&[api_type as u8])?;
+
+        if matches!(api_type, ApiType::Pti | ApiType::Zniffer) {
+            self.frame_counter += 1;
+        }
+        Ok(())
+    }
+
+    /// Write a record holding Zniffer API bytes, e.g., as received from a Zniffer device.
+    pub fn write_zniffer(&mut self, timestamp: ZlfTimestamp, payload: &[u8]) -> Result<(), ZlfError> {
+        self.write_raw(timestamp, ZlfProperties::default(), ApiType::Zniffer, payload)
+    }
+
+    /// Write a record holding a PTI frame.
+    pub fn write_pti(&mut self, timestamp: ZlfTimestamp, payload: &[u8]) -> Result<(), ZlfError> {
+        self.write_raw(timestamp, ZlfProperties::default(), ApiType::Pti, payload)
+    }
+
+    /// Write an attachment record.
+    pub fn write_attachment(&mut self, timestamp: ZlfTimestamp, data: &[u8]) -> Result<(), ZlfError> {
+        self.write_raw(timestamp, ZlfProperties::default(), ApiType::Attachment, data)
+    }
+
+    /// Write a record as returned by `ZlfReader` or `ZlfFrames`, preserving
+    /// its timestamp and properties. Decoded data and beam frames are
+    /// re-encoded as Zniffer API bytes.
+    pub fn write_record(&mut self, record: &ZlfRecord) -> Result<(), ZlfError> {
+        match record {
+            ZlfRecord::Attachment(attachment) => {
+                self.write_raw(attachment.timestamp, attachment.properties, ApiType::Attachment, &attachment.data)
+            },
+            ZlfRecord::Other(RawFrame { timestamp, properties, api_type, payload, .. }) => {
+                self.write_raw(*timestamp, *properties, *api_type, payload)
+            },
+            ZlfRecord::Data(df) if df.frame.frame_type != FrameType::Command => {
+                self.write_raw(df.timestamp, df.properties, ApiType::Zniffer, &df.frame.to_zniffer_vector())
+            },
+            ZlfRecord::Data(_) => Err(ZlfError::UnsupportedRecord),
+        }
+    }
+
+    /// Flush buffered data and return the underlying writer.
+    pub fn finish(mut self) -> Result<W, ZlfError> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::zlf::ZlfReader;
+    use crate::zlf::types::{
+        ZLF_HEADER_SIZE,
+        ZLF_VERSION,
+    };
+
+    fn synthetic_trace() -> Vec<u8> {
+        let header = ZlfHeader::with_comment("TC-42 round trip");
+        let mut writer = ZlfWriter::with_header(Cursor::new(Vec::new()), &header).unwrap();
+        writer.write_zniffer(ZlfTimestamp(0x48DD_1C5A_7B2E_0000), &[
+            0x21, 0x01, 0x6D, 0xCE, 0x20, 0x00, 0x9D, 0x21, 0x03, 0x0A,
+            0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x81, 0x0D, 0x12, 0x20, 0x0B,
+        ]).unwrap();
+        writer.write_raw(ZlfTimestamp(0x48DD_1C5A_7B2E_1000), ZlfProperties(0x81), ApiType::Zniffer, &[0x23, 0x04, 0x00]).unwrap();
+        writer.write_attachment(ZlfTimestamp(0x48DD_1C5A_7B2E_2000), b"session notes").unwrap();
+        writer.write_pti(ZlfTimestamp(0x48DD_1C5A_7B2E_3000), &[0x5B, 0x04, 0x00, 0x02, 0xF8, 0x5D]).unwrap();
+        writer.write_zniffer(ZlfTimestamp(0x48DD_1C5A_7B2E_4000), &[]).unwrap();
+        assert_eq!(writer.frame_count(), 4);
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_header() {
+        let bytes = ZlfWriter::new(Cursor::new(Vec::new())).unwrap().finish().unwrap().into_inner();
+        assert_eq!(bytes.len(), ZLF_HEADER_SIZE);
+        assert_eq!(&bytes[0..4], &ZLF_VERSION.to_le_bytes());
+
+        // The reader verifies the header checksum.
+        let mut reader = ZlfReader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = synthetic_trace();
+
+        let mut reader = ZlfReader::new(Cursor::new(original.clone())).unwrap();
+        assert_eq!(reader.header().comment, "TC-42 round trip");
+        let mut writer = ZlfWriter::with_header(Cursor::new(Vec::new()), reader.header()).unwrap();
+        while let Some(record) = reader.next().unwrap() {
+            writer.write_record(&record).unwrap();
+        }
+        assert_eq!(reader.frame_count(), writer.frame_count());
+
+        let copy = writer.finish().unwrap().into_inner();
+        assert_eq!(original, copy);
     }
 }
@@ -0,0 +1,272 @@
+// SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
+// SPDX-License-Identifier: MIT
+//! Requests and responses of the Zniffer API commands.
+//!
+//! A request is `[ 23, id, len, <payload> ]` and the device answers with a
+//! command of the same ID, as returned by `zniffer_parser::Parser` in
+//! `ParserResult::ValidCommand`.
+use std::io;
+use thiserror::Error;
+use crate::types::Region;
+use crate::zniffer_parser::SOF_COMMAND;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandId {
+    GetVersion = 0x01,
+    SetRegion = 0x02,
+    GetRegions = 0x03,
+    Start = 0x04,
+    Stop = 0x05,
+    SetLrChannelConfig = 0x06,
+    GetLrChannelConfigs = 0x07,
+    SetBaudRate = 0x0E,
+    GetRegionName = 0x13,
+}
+
+impl TryFrom<u8> for CommandId {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(CommandId::GetVersion),
+            0x02 => Ok(CommandId::SetRegion),
+            0x03 => Ok(CommandId::GetRegions),
+            0x04 => Ok(CommandId::Start),
+            0x05 => Ok(CommandId::Stop),
+            0x06 => Ok(CommandId::SetLrChannelConfig),
+            0x07 => Ok(CommandId::GetLrChannelConfigs),
+            0x0E => Ok(CommandId::SetBaudRate),
+            0x13 => Ok(CommandId::GetRegionName),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Serial baud rates supported by the Zniffer.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaudRate {
+    B115200 = 0x00,
+    B230400 = 0x01,
+}
+
+impl BaudRate {
+    pub fn bits_per_second(&self) -> u32 {
+        match self {
+            BaudRate::B115200 => 115_200,
+            BaudRate::B230400 => 230_400,
+        }
+    }
+}
+
+impl TryFrom<u32> for BaudRate {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            115_200 => Ok(BaudRate::B115200),
+            230_400 => Ok(BaudRate::B230400),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    GetVersion,
+    SetRegion(Region),
+    /// List the region codes the device supports.
+    GetRegions,
+    Start,
+    Stop,
+    /// Select one of the Long Range channel configurations.
+    SetLrChannelConfig(u8),
+    GetLrChannelConfigs,
+    /// The Zniffer switches to the new rate after responding.
+    SetBaudRate(BaudRate),
+    /// Query the name of a region code, e.g., as returned by `GetRegions`.
+    GetRegionName(u8),
+}
+
+impl Request {
+    pub fn id(&self) -> CommandId {
+        match self {
+            Request::GetVersion => CommandId::GetVersion,
+            Request::SetRegion(_) => CommandId::SetRegion,
+            Request::GetRegions => CommandId::GetRegions,
+            Request::Start => CommandId::Start,
+            Request::Stop => CommandId::Stop,
+            Request::SetLrChannelConfig(_) => CommandId::SetLrChannelConfig,
+            Request::GetLrChannelConfigs => CommandId::GetLrChannelConfigs,
+            Request::SetBaudRate(_) => CommandId::SetBaudRate,
+            Request::GetRegionName(_) => CommandId::GetRegionName,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let payload = match self {
+            Request::SetRegion(region) => vec![*region as u8],
+            Request::SetLrChannelConfig(config) => vec![*config],
+            Request::SetBaudRate(baud_rate) => vec![*baud_rate as u8],
+            Request::GetRegionName(code) => vec![*code],
+            _ => vec![],
+        };
+        let mut bytes = vec![SOF_COMMAND, self.id() as u8, payload.len() as u8];
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub chip_type: u8,
+    pub chip_version: u8,
+    pub major: u8,
+    pub minor: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    Version(Version),
+    /// Region codes, which may include codes unknown to `Region`.
+    Regions(Vec<u8>),
+    LrChannelConfigs {
+        current: u8,
+        supported: Vec<u8>,
+    },
+    RegionName {
+        channels: u8,
+        name: String,
+    },
+    /// Acknowledgement of a request without response data.
+    Done(CommandId),
+}
+
+impl Response {
+    /// Decode the payload of the device's answer to a request.
+    ///
+    /// - Get Version: chip type, chip version, major, minor.
+    /// - Get Regions: one region code per byte.
+    /// - Get LR Channel Configs: current configuration followed by the
+    ///   supported ones.
+    /// - Get Region Name: number of channels followed by the name.
+    pub fn parse(id: CommandId, payload: &[u8]) -> Result<Self, ZnifferError> {
+        match (id, payload) {
+            (CommandId::GetVersion, [chip_type, chip_version, major, minor, ..]) => {
+                Ok(Response::Version(Version {
+                    chip_type: *chip_type,
+                    chip_version: *chip_version,
+                    major: *major,
+                    minor: *minor,
+                }))
+            },
+            (CommandId::GetVersion, _) => Err(ZnifferError::InvalidResponse(id)),
+            (CommandId::GetRegions, codes) => Ok(Response::Regions(codes.to_vec())),
+            (CommandId::GetLrChannelConfigs, [current, supported @ ..]) => {
+                Ok(Response::LrChannelConfigs { current: *current, supported: supported.to_vec() })
+            },
+            (CommandId::GetLrChannelConfigs, _) => Err(ZnifferError::InvalidResponse(id)),
+            (CommandId::GetRegionName, [channels, name @ ..]) => {
+                let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+                Ok(Response::RegionName {
+                    channels: *channels,
+                    name: String::from_utf8_lossy(&name[..end]).into_owned(),
+                })
+            },
+            (CommandId::GetRegionName, _) => Err(ZnifferError::InvalidResponse(id)),
+            _ => Ok(Response::Done(id)),
+        }
+    }
+
+    /// ID of the request this is the response to.
+    pub fn id(&self) -> CommandId {
+        match self {
+            Response::Version(_) => CommandId::GetVersion,
+            Response::Regions(_) => CommandId::GetRegions,
+            Response::LrChannelConfigs { .. } => CommandId::GetLrChannelConfigs,
+            Response::RegionName { .. } => CommandId::GetRegionName,
+            Response::Done(id) => *id,
+        }
+    }
+
+    /// Encode the response as sent by a device, i.e., the inverse of
+    /// `parse()`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let payload = match self {
+            Response::Version(version) => {
+                vec![version.chip_type, version.chip_version, version.major, version.minor]
+            },
+            Response::Regions(codes) => codes.clone(),
+            Response::LrChannelConfigs { current, supported } => {
+                let mut payload = vec![*current];
+                payload.extend_from_slice(supported);
+                payload
+            },
+            Response::RegionName { channels, name } => {
+                let mut payload = vec![*channels];
+                payload.extend_from_slice(name.as_bytes());
+                payload
+            },
+            Response::Done(_) => vec![],
+        };
+        let mut bytes = vec![SOF_COMMAND, self.id() as u8, payload.len() as u8];
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ZnifferError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("timed out waiting for the response to {0:?}")]
+    Timeout(CommandId),
+    #[error("invalid response to {0:?}")]
+    InvalidResponse(CommandId),
+    #[error("capture stopped")]
+    Stopped,
+    #[error("device disconnected")]
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests() {
+        assert_eq!(Request::GetVersion.to_bytes(), vec![0x23, 0x01, 0x00]);
+        assert_eq!(Request::SetRegion(Region::US).to_bytes(), vec![0x23, 0x02, 0x01, 0x01]);
+        assert_eq!(Request::SetBaudRate(BaudRate::B230400).to_bytes(), vec![0x23, 0x0E, 0x01, 0x01]);
+        assert_eq!(Request::GetRegionName(0x09).to_bytes(), vec![0x23, 0x13, 0x01, 0x09]);
+        assert_eq!(CommandId::try_from(0x13), Ok(CommandId::GetRegionName));
+    }
+
+    #[test]
+    fn test_responses() {
+        assert_eq!(
+            Response::parse(CommandId::GetVersion, &[0x08, 0x00, 0x02, 0x11]).unwrap(),
+            Response::Version(Version { chip_type: 8, chip_version: 0, major: 2, minor: 17 }),
+        );
+        assert!(matches!(
+            Response::parse(CommandId::GetVersion, &[0x08]),
+            Err(ZnifferError::InvalidResponse(CommandId::GetVersion)),
+        ));
+        assert_eq!(Response::parse(CommandId::GetRegions, &[0x00, 0x01, 0x09]).unwrap(), Response::Regions(vec![0, 1, 9]));
+        assert_eq!(
+            Response::parse(CommandId::GetLrChannelConfigs, &[0x01, 0x00, 0x01, 0x02]).unwrap(),
+            Response::LrChannelConfigs { current: 1, supported: vec![0, 1, 2] },
+        );
+        assert_eq!(
+            Response::parse(CommandId::GetRegionName, b"\x03US_LR\0").unwrap(),
+            Response::RegionName { channels: 3, name: "US_LR".to_string() },
+        );
+        assert_eq!(Response::parse(CommandId::Stop, &[]).unwrap(), Response::Done(CommandId::Stop));
+
+        let response = Response::RegionName { channels: 4, name: "USLR".to_string() };
+        let bytes = response.to_bytes();
+        assert_eq!(&bytes[..3], &[0x23, 0x13, 0x05]);
+        assert_eq!(Response::parse(response.id(), &bytes[3..]).unwrap(), response);
+    }
+}
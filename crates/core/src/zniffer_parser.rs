@@ -1,12 +1,26 @@
 // SPDX-FileCopyrightText: Trident IoT, LLC <https://www.tridentiot.com>
 // SPDX-License-Identifier: MIT
+//! Parser for the Zniffer API, i.e., the bytes sent by a Zniffer device and
+//! stored in ZLF Zniffer (0xFE) records.
+//!
+//! ```text
+//! Command:              [ 23, id, len, <payload> ]
+//! Data frame:           [ 21, 01, ts_hi, ts_lo, ch << 5 | speed, region, rssi, 21, 03, len, <MPDU> ]
+//! Beam, start and stop: [ 21, type, ts_hi, ts_lo, ch << 5 | speed, region, rssi, len, <payload> ]
+//! ```
+//!
+//! Beam frames lack the start of data sequence. The payload of a beam and a
+//! beam start is the beam fragment, i.e., the beam tag and the destination
+//! node ID, while the payload of a beam stop holds the fragment count.
 use crate::types::{
   Frame,
+  FrameType,
   Region,
 };
+use crate::zniffer_command::CommandId;
 
-const SOF_COMMAND: u8 = 0x23;
-const SOF_FRAME: u8 = 0x21;
+pub const SOF_COMMAND: u8 = 0x23;
+pub const SOF_FRAME: u8 = 0x21;
 
 #[derive(Debug)]
 #[derive(PartialEq)]
@@ -50,6 +64,12 @@ pub struct Parser {
   frame: Frame,
 }
 
+impl Default for Parser {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl Parser {
   pub fn new() -> Self {
     Parser {
@@ -101,7 +121,7 @@ impl Parser {
         },
         ParserState::AwaitCommandID => {
           self.command_id = value;
-          if matches!(self.command_id, 1 | 2 | 3 | 19 | 4 | 5 | 14) {
+          if CommandId::try_from(value).is_ok() {
             self.state = ParserState::AwaitLength;
           } else {
             // Unsupported command. Reset.
@@ -110,8 +130,9 @@ impl Parser {
           }
         },
         ParserState::AwaitType => {
-          if matches!(value, 1 | 2 | 4 | 5) {
+          if let Ok(frame_type @ (FrameType::Data | FrameType::Beam | FrameType::BeamStart | FrameType::BeamStop)) = FrameType::try_from(value) {
             self.frame_type = value;
+            self.frame.frame_type = frame_type;
             self.state = ParserState::AwaitTimestamp;
           } else {
             self.reset();
@@ -119,7 +140,7 @@ impl Parser {
           }
         },
         ParserState::AwaitTimestamp => {
-          if self.timestamp_state == false {
+          if !self.timestamp_state {
             self.frame.timestamp = (value as u16) << 8;
             self.timestamp_state = true;
           } else {
@@ -147,7 +168,12 @@ impl Parser {
         },
         ParserState::AwaitRssi => {
           self.frame.rssi = value;
-          self.state = ParserState::AwaitStartofDataOne;
+          self.state = if self.frame.frame_type == FrameType::Data {
+            ParserState::AwaitStartofDataOne
+          } else {
+            // Beams have no start of data sequence.
+            ParserState::AwaitLength
+          };
         },
         ParserState::AwaitStartofDataOne => {
           if value == 0x21 {
@@ -169,10 +195,8 @@ impl Parser {
           self.length = value;
           self.payload_count = self.length;
           if self.length == 0 {
-            // No payload, so we can return the frame immediately.
-            let result: ParserResult;
-            // TODO: Check for command vs. frame. Assuming command for now.
-            result = ParserResult::ValidCommand { id: self.command_id, payload: vec![] };
+            // No payload, so we can return the command or frame immediately.
+            let result = self.result();
             self.reset();
             return result;
           } else {
@@ -183,14 +207,7 @@ impl Parser {
           self.frame.payload.push(value);
           self.payload_count -= 1;
           if self.payload_count < 1 {
-            let result: ParserResult;
-            if matches!(self.frame_type, 1 | 2 | 4 | 5) {
-              // Return a clone of the valid frame because this function
-              // will continue parsing and overwrite self.frame.
-              result = ParserResult::ValidFrame { frame: self.frame.clone() };
-            } else {
-              result = ParserResult::ValidCommand { id: self.command_id, payload: self.frame.payload.clone() };
-            }
+            let result = self.result();
             self.reset();
             return result;
           }
@@ -203,6 +220,17 @@ impl Parser {
   pub fn timeout(&mut self) {
     self.reset();
   }
+
+  /// The parsed command or frame. The frame type is 0 while parsing a command.
+  fn result(&self) -> ParserResult {
+    if self.frame_type != 0 {
+      // Return a clone of the valid frame because this function
+      // will continue parsing and overwrite self.frame.
+      ParserResult::ValidFrame { frame: self.frame.clone() }
+    } else {
+      ParserResult::ValidCommand { id: self.command_id, payload: self.frame.payload.clone() }
+    }
+  }
 }
 
 #[cfg(test)]
@@ -250,8 +278,9 @@ mod tests {
             0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x81,
             0x0D, 0x12, 0x20, 0x0B, 0x10, 0x02,
             0x41, 0x7F, 0x7F, 0x7F, 0x7F, 0xE5,
-          ]
-        }
+          ],
+          ..Default::default()
+        },
        });
 
       let result = parser.parse_bytes(vec![
@@ -286,8 +315,62 @@ mod tests {
             0xE5, 0x07, 0x76, 0x83, 0x01, 0x41, 0x0C,
             0x15, 0x31, 0x98, 0x80, 0x3F, 0xF0, 0x2A,
             0xE0, 0x8C, 0x27, 0x72, 0x3D, 0xF1, 0x14,
-          ]
-        }
+          ],
+          ..Default::default()
+        },
        });
     }
+
+    #[test]
+    fn test_zniffer_vector_round_trip() {
+      let frame = Frame {
+        region: Region::US,
+        channel: 0x02,
+        speed: 0x01,
+        timestamp: 0x1234,
+        rssi: 0xB0,
+        payload: vec![0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x41, 0x0C, 0x0B, 0x02],
+        ..Default::default()
+      };
+
+      let mut parser = Parser::new();
+      let result = parser.parse_bytes(frame.to_zniffer_vector());
+      assert_eq!(result, ParserResult::ValidFrame { frame });
+    }
+
+    #[test]
+    fn test_beams() {
+      let mut parser = Parser::new();
+
+      let result = parser.parse_bytes(vec![
+        0x21, 0x04, // Beam start
+        0x12, 0x34, // Timestamp
+        0x20, // Channel and speed
+        0x01, // Region
+        0xB4, // RSSI
+        0x02, // Length
+        0x55, 0x07, // Beam tag and node ID
+      ]);
+      let ParserResult::ValidFrame { frame: start } = result else {
+        panic!("expected a valid frame, got {:?}", result);
+      };
+      assert_eq!(start.frame_type, FrameType::BeamStart);
+      assert_eq!(start.region, Region::US);
+      assert_eq!(start.channel, 1);
+      assert_eq!(start.payload, vec![0x55, 0x07]);
+
+      let stop = Frame {
+        frame_type: FrameType::BeamStop,
+        timestamp: 0x1680,
+        payload: vec![0x00, 0x30],
+        ..start.clone()
+      };
+      let bytes = stop.to_zniffer_vector();
+      assert_eq!(&bytes[..8], &[0x21, 0x05, 0x16, 0x80, 0x20, 0x01, 0xB4, 0x02]);
+      assert_eq!(parser.parse_bytes(bytes), ParserResult::ValidFrame { frame: stop });
+
+      // Beam without payload.
+      let result = parser.parse_bytes(vec![0x21, 0x05, 0x00, 0x01, 0x20, 0x01, 0xB4, 0x00]);
+      assert!(matches!(result, ParserResult::ValidFrame { frame } if frame.frame_type == FrameType::BeamStop));
+    }
 }
@@ -2,16 +2,48 @@
 // SPDX-License-Identifier: MIT
 use std::panic;
 use tracing::error;
-use tokio;
 
 use clap::Parser;
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
 use zniff_rs_core::zlf::{
+    ZlfError,
+    ZlfFrames,
+    ZlfIndex,
     ZlfReader,
     ZlfRecord,
+    types::ZlfTimestamp,
 };
-use zniff_rs_core::zniffer_parser;
 use zniff_rs_core::storage::{FrameDatabase, SqliteFrameDatabase, DbFrame};
+use zniff_rs_core::beam::BeamTracker;
+use zniff_rs_core::decoder::{
+    DecodedFrame,
+    Decoder,
+    Definitions,
+};
+use zniff_rs_core::device::{
+    self,
+    Transport,
+    Zniffer,
+    RESPONSE_TIMEOUT,
+};
+use zniff_rs_core::types::{
+    Frame,
+    FrameType,
+    Region,
+};
+use zniff_rs_core::mac::{
+    self,
+    Direction,
+    ExploreCommand,
+    HeaderType,
+    MacHeader,
+};
+use zniff_rs_core::zniffer_command::ZnifferError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 use ratatui::{
     Terminal,
@@ -54,96 +86,344 @@ use ratatui::{
 };
 use std::io;
 
+/// Number of frame records loaded before the last one when jumping to the
+/// end of a trace.
+const END_WINDOW: usize = 1000;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum AppMode {
     Normal,
     Detail,
 }
 
-struct Frame {
-    id: u64,
-    timestamp: u64,
-    timestamp_delta: u64,
-    speed: u8,
-    rssi: i8,
-    channel: u8,
-    home_id: u32,
-    src_node_id: u16,
-    dst_node_id: u16,
-    payload: String,
-    payload_raw: Vec<u8>,
+/// Events sent by the device task to the app.
+enum DeviceEvent {
+    Frame(Frame),
+    Capturing(bool),
+    Error(String),
+}
+
+enum Source {
+    /// Frames read from a trace as they are scrolled into view, from the
+    /// start or from a record located through the index.
+    Trace {
+        frames: Box<ZlfFrames<BufReader<File>>>,
+        index: ZlfIndex,
+        /// Number of the frame record the database starts at.
+        start: usize,
+        /// `frames.frame_count()` when the reader was at `start`.
+        start_count: usize,
+    },
+    /// Frames captured by a Zniffer device.
+    Live {
+        events: mpsc::UnboundedReceiver<DeviceEvent>,
+        /// Starts (true) or stops (false) the capture.
+        control: mpsc::UnboundedSender<bool>,
+        task: JoinHandle<()>,
+        capturing: bool,
+        error: Option<String>,
+    },
+}
+
+impl Source {
+    /// Number of frame records of a trace read so far, counting those
+    /// skipped by seeking. The last frame read came from this record.
+    fn record(&self) -> Option<usize> {
+        match self {
+            Source::Trace { frames, start, start_count, .. } => Some(start + frames.frame_count() - start_count),
+            Source::Live { .. } => None,
+        }
+    }
 }
 
-struct App {
+struct App<'a> {
+    /// IDs of the listed frames.
     items: Vec<u128>,
+    /// Number of frames in the database.
+    frame_count: usize,
+    /// Only list frames addressed to this node, including multicasts.
+    node_filter: Option<u16>,
     state: TableState,
     mode: AppMode,
     db: SqliteFrameDatabase,
+    source: Source,
+    /// Set once all frames of the trace are in the database.
+    loaded: bool,
+    /// Keep the last frame selected as frames arrive.
+    follow: bool,
+    beams: BeamTracker,
+    /// Beam durations in milliseconds by the ID of the beam stop frame.
+    beam_durations: HashMap<i64, u16>,
+    /// Decodes the commands of data frames in capture order, learning the
+    /// command class versions of the nodes.
+    decoder: Decoder<'a>,
+    /// Decoded commands of the detail popup by frame ID.
+    commands: HashMap<i64, String>,
+    /// Number of the frame record of a trace, counting from 1, by frame ID.
+    /// Shown instead of the ID, which depends on where loading started.
+    records: HashMap<i64, usize>,
 }
 
-impl App {
-    fn try_new(file: File) -> Result<App, bool> {
+impl<'a> App<'a> {
+    fn with_source(source: Source, definitions: &'a Definitions) -> App<'a> {
         let mut state = TableState::default();
         state.select(Some(0));
 
-        let mut zlf_reader = ZlfReader::new(file).expect("Failed to create ZLF reader");
-
-        let db = SqliteFrameDatabase::new();
-
-        let mut items: Vec<u128> = Vec::new();
-        let mut frame_id: u128 = 1;
-
-        let mut zniffer_parser = zniffer_parser::Parser::new();
-
-        zlf_reader.read_frames(|rec| {
-            match rec {
-                ZlfRecord::Other(raw) => {
-                    for byte in raw.payload.iter() {
-                        let result = zniffer_parser.parse(*byte);
-
-                        match result {
-                            zniffer_parser::ParserResult::ValidFrame { frame } => {
-                                items.push(frame_id);
-
-                                let db_frame = DbFrame {
-                                    id: frame_id as i64, // You can generate or extract an ID for the frame
-                                    timestamp: frame.timestamp as i64, // You can extract this from the frame if needed
-                                    speed: frame.speed,     // You can extract this from the frame if needed
-                                    rssi: frame.rssi as i8,      // You can extract this from the frame if needed
-                                    channel: frame.channel,   // You can extract this from the frame if needed
-                                    home_id: 0x12345678, // Example home_id, replace with actual value if available
-                                    src_node_id: 1, // Example src_node_id, replace with actual value if available
-                                    dst_node_id: 2, // Example dst_node_id, replace with actual value if available
-                                    payload: frame.payload.clone(), // Use the raw payload from the parsed frame
-                                };
-
-                                //println!("Insert frame");
-                                db.add_frame(db_frame);
-                                frame_id += 1;
-                            },
-                            _ => {
-                                // Don't care about other parser results than a valid frame for now.
-                            },
-                        }
+        App {
+            items: Vec::new(),
+            frame_count: 0,
+            node_filter: None,
+            state,
+            mode: AppMode::Normal,
+            db: SqliteFrameDatabase::new(),
+            source,
+            loaded: false,
+            follow: false,
+            beams: BeamTracker::new(),
+            beam_durations: HashMap::new(),
+            decoder: Decoder::with_definitions(definitions),
+            commands: HashMap::new(),
+            records: HashMap::new(),
+        }
+    }
+
+    /// Open a trace. Frames are loaded into the database as they are scrolled
+    /// into view, so huge traces open instantly.
+    fn try_new(path: &str, definitions: &'a Definitions) -> Result<App<'a>, ZlfError> {
+        let index = ZlfIndex::open(path)?;
+        let zlf_reader = ZlfReader::new(BufReader::new(File::open(path)?))?;
+
+        let mut app = App::with_source(Source::Trace {
+            frames: Box::new(zlf_reader.into_frames()),
+            index,
+            start: 0,
+            start_count: 0,
+        }, definitions);
+        app.load_until(1);
+        Ok(app)
+    }
+
+    /// Start a capture with the Zniffer device on a serial port.
+    async fn try_live(port: &str, region: Region, definitions: &'a Definitions) -> Result<App<'a>, ZnifferError> {
+        let mut zniffer = Zniffer::new(device::open_serial(port, device::DEFAULT_BAUD_RATE)?);
+        zniffer.set_region(region).await?;
+        zniffer.start().await?;
+
+        let (event_tx, events) = mpsc::unbounded_channel();
+        let (control, control_rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(drive_device(zniffer, event_tx, control_rx));
+
+        let mut app = App::with_source(Source::Live {
+            events,
+            control,
+            task,
+            capturing: true,
+            error: None,
+        }, definitions);
+        app.loaded = true;
+        app.follow = true;
+        Ok(app)
+    }
+
+    /// Stop a capture and wait for the device to confirm.
+    async fn shutdown(self) {
+        if let Source::Live { control, task, .. } = self.source {
+            drop(control);
+            let _ = tokio::time::timeout(RESPONSE_TIMEOUT, task).await;
+        }
+    }
+
+    /// Store a frame in the database.
+    fn add(&mut self, timestamp: ZlfTimestamp, frame: Frame) {
+        self.frame_count += 1;
+        let frame_id = self.frame_count as u128;
+
+        if let Some(beam) = self.beams.push(&frame) {
+            self.beam_durations.insert(frame_id as i64, beam.duration_ms);
+        }
+        if frame.frame_type == FrameType::Data {
+            let decoded = self.decoder.decode_frame(&frame);
+            self.commands.insert(frame_id as i64, format_command(&decoded));
+        }
+        // Beams carry no MAC header, only the destination node ID.
+        let mac_header = match frame.frame_type {
+            FrameType::Data => frame.mac_header().ok(),
+            _ => None,
+        };
+        let dst_node_id = match (frame.beam_target(), &mac_header) {
+            (Some(target), _) => target,
+            (None, Some(header)) => header.dst_node_id.unwrap_or(0),
+            (None, None) => 0,
+        };
+        let db_frame = DbFrame {
+            id: frame_id as i64, // You can generate or extract an ID for the frame
+            frame_type: frame.frame_type as u8,
+            region: frame.region as u8,
+            timestamp: timestamp.unix_micros(), // Capture time from the ZLF record
+            speed: frame.speed,
+            rssi: frame.rssi as i8,
+            channel: frame.channel,
+            home_id: mac_header.as_ref().map_or(0, |header| header.home_id),
+            header_type: mac_header.as_ref().map_or(0, |header| header.header_type as u8),
+            src_node_id: mac_header.as_ref().map_or(0, |header| header.src_node_id),
+            dst_node_id,
+            checksum_ok: frame.frame_type != FrameType::Data || frame.checksum_ok(),
+            multicast: mac_header.and_then(|header| header.multicast).unwrap_or_default(),
+            payload: frame.payload, // Use the raw payload from the parsed frame
+        };
+
+        if self.node_filter.is_none_or(|node_id| db_frame.is_addressed_to(node_id)) {
+            self.items.push(frame_id);
+        }
+        self.db.add_frame(db_frame);
+    }
+
+    /// Listed frames, see `node_filter`.
+    fn frames(&self, offset: usize, limit: usize) -> Vec<DbFrame> {
+        match self.node_filter {
+            Some(node_id) => self.db.get_frames_to_node(node_id, offset, limit),
+            None => self.db.get_frames(offset, limit),
+        }
+    }
+
+    /// List only the frames addressed to a node, or all frames. Keeps the
+    /// selected frame selected if it is still listed.
+    fn filter(&mut self, node_filter: Option<u16>) {
+        let selected = self.state.selected().and_then(|i| self.items.get(i).copied());
+        self.node_filter = node_filter;
+        self.items = match node_filter {
+            Some(node_id) => self
+                .db
+                .get_frames_to_node(node_id, 0, self.frame_count)
+                .iter()
+                .map(|frame| frame.id as u128)
+                .collect(),
+            None => {
+(1..=self.frame_count as u128).collect()
+            },
+        };
+        let i = selected.and_then(|id| self.items.iter().position(|item| *item == id));
+        self.state.select(Some(i.unwrap_or(0)));
+        self.load_until(1);
+    }
+
+    /// Filter on the destination of the selected frame, or clear the filter.
+    fn toggle_filter(&mut self) {
+        if self.node_filter.is_some() {
+            self.filter(None);
+            return;
+        }
+        let selected = self.state.selected().and_then(|i| self.items.get(i));
+        if let Some(frame) = selected.and_then(|id| self.db.get_frame(*id as u64))
+            && frame.multicast.is_empty()
+        {
+            self.filter(Some(frame.dst_node_id));
+        }
+    }
+
+    /// Decode frames into the database until `count` frames are loaded or
+    /// the end of the trace is reached.
+    fn load_until(&mut self, count: usize) {
+        while !self.loaded && self.items.len() < count {
+            let Source::Trace { frames, .. } = &mut self.source else {
+                return;
+            };
+            match frames.next() {
+                Some(Ok(ZlfRecord::Data(data_frame))) => {
+                    self.add(data_frame.timestamp, data_frame.frame);
+                    if let Some(record) = self.source.record() {
+                        self.records.insert(self.frame_count as i64, record);
                     }
                 },
-                _ => {
+                Some(Ok(_)) => {
                     // Don't care about other record types for now.
-                }
+                },
+                Some(Err(e)) => {
+                    error!(?e, "failed to read frames from ZLF file");
+                    self.loaded = true;
+                },
+                None => self.loaded = true,
             }
-        }).expect("Failed to read frames from ZLF file");
+        }
+    }
 
-        Ok(App {
-            items,
-            state,
-            mode: AppMode::Normal,
-            db
-        })
+    /// Empty the database and load frames from frame record `start` on,
+    /// seeking to it through the index.
+    fn load_from(&mut self, start: usize) {
+        let Source::Trace { frames, index, start: first, start_count } = &mut self.source else {
+            return;
+        };
+        let Some(entry) = index.frame(start) else {
+            return;
+        };
+        if let Err(e) = frames.seek_record(entry) {
+            error!(?e, "failed to seek in ZLF file");
+            return;
+        }
+        *first = start;
+        *start_count = frames.frame_count();
+
+        self.db = SqliteFrameDatabase::new();
+        self.items.clear();
+        self.frame_count = 0;
+        self.beams = BeamTracker::new();
+        self.beam_durations.clear();
+        self.commands.clear();
+        self.records.clear();
+        self.loaded = false;
+        self.load_until(1);
+    }
+
+    /// The record number of a frame of a trace, or the ID of a captured frame.
+    fn frame_number(&self, frame: &DbFrame) -> String {
+        match self.records.get(&frame.id) {
+            Some(record) => record.to_string(),
+            None => frame.id.to_string(),
+        }
+    }
+
+    fn frame_number_label(&self) -> &'static str {
+        match self.source {
+            Source::Trace { .. } => "Record",
+            Source::Live { .. } => "ID",
+        }
+    }
+
+    /// Store the frames captured since the last call.
+    fn poll_device(&mut self) {
+        loop {
+            let Source::Live { events, capturing, error, .. } = &mut self.source else {
+                return;
+            };
+            match events.try_recv() {
+                Ok(DeviceEvent::Frame(frame)) => {
+                    self.add(ZlfTimestamp::now(), frame);
+                    // Frames hidden by the node filter are not listed.
+                    if self.follow && !self.items.is_empty() {
+                        self.state.select(Some(self.items.len() - 1));
+                    }
+                },
+                Ok(DeviceEvent::Capturing(started)) => {
+                    *capturing = started;
+                    *error = None;
+                },
+                Ok(DeviceEvent::Error(e)) => {
+                    error!(e, "Zniffer device failed");
+                    *error = Some(e);
+                },
+                Err(_) => return,
+            }
+        }
     }
 
     fn next(&mut self) {
+        if self.items.is_empty() {
+            // Nothing captured yet.
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
+                self.load_until(i + 2);
                 if i >= self.items.len() - 1 {
                     0
                 } else {
@@ -156,6 +436,11 @@ impl App {
     }
 
     fn previous(&mut self) {
+        if self.items.is_empty() {
+            // Nothing captured yet.
+            return;
+        }
+        self.follow = false;
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -170,14 +455,22 @@ impl App {
     }
 
     fn page_down(&mut self, page_size: usize) {
+        if self.items.is_empty() {
+            // Nothing captured yet.
+            return;
+        }
         let i = match self.state.selected() {
-            Some(i) => (i + page_size).min(self.items.len() - 1),
+            Some(i) => {
+                self.load_until(i + page_size + 1);
+                (i + page_size).min(self.items.len() - 1)
+            },
             None => 0,
         };
         self.state.select(Some(i));
     }
 
     fn page_up(&mut self, page_size: usize) {
+        self.follow = false;
         let i = match self.state.selected() {
             Some(i) => i.saturating_sub(page_size),
             None => 0,
@@ -186,51 +479,44 @@ impl App {
     }
 
     fn go_to_start(&mut self) {
+        self.follow = false;
+        if matches!(self.source, Source::Trace { start, .. } if start > 0) {
+            self.load_from(0);
+        }
         self.state.select(Some(0));
     }
 
+    /// Select the last frame and, when capturing, keep following new frames.
+    /// Of a trace, only the last `END_WINDOW` frame records are loaded.
     fn go_to_end(&mut self) {
+        if let Source::Trace { index, .. } = &self.source
+            && !self.loaded
+        {
+            let start = index.frame_count().saturating_sub(END_WINDOW);
+            if self.source.record().is_some_and(|record| record < start) {
+                self.load_from(start);
+            }
+        }
+        self.load_until(usize::MAX);
+        self.follow = matches!(self.source, Source::Live { .. });
         if !self.items.is_empty() {
             self.state.select(Some(self.items.len() - 1));
         }
     }
 
-    fn add(&mut self, _item: Frame) {
-        // TODO: Modify this function to actually add the frame to the database and update the items list accordingly.
-        //self.items.push(item);
-        self.state.select(Some(self.items.len() - 1));
-    }
-
-    fn start(&mut self) {
-        self.add(Frame {
-            timestamp: 0,
-            src_node_id: 0,
-            dst_node_id: 0,
-            home_id: 0,
-            id: 0,
-            timestamp_delta: 0,
-            speed: 0,
-            rssi: 0,
-            channel: 0,
-            payload: String::new(),
-            payload_raw: Vec::new(),
-        });
+    fn toggle_follow(&mut self) {
+        if self.follow {
+            self.follow = false;
+        } else {
+            self.go_to_end();
+        }
     }
 
-    fn stop(&mut self) {
-        self.add(Frame {
-            timestamp: 0,
-            src_node_id: 0,
-            dst_node_id: 0,
-            home_id: 0,
-            id: 0,
-            timestamp_delta: 0,
-            speed: 0,
-            rssi: 0,
-            channel: 0,
-            payload: String::new(),
-            payload_raw: Vec::new(),
-        });
+    /// Resume (true) or pause (false) a capture.
+    fn capture(&mut self, start: bool) {
+        if let Source::Live { control, .. } = &self.source {
+            let _ = control.send(start);
+        }
     }
 
     fn toggle_detail(&mut self) {
@@ -278,28 +564,20 @@ impl App {
                 self.go_to_end();
                 Ok(false)
             }
+            KeyCode::Char('f') => {
+                self.toggle_follow();
+                Ok(false)
+            }
             KeyCode::Char('n') => {
-                self.add(Frame {
-                    timestamp: 0,
-                    src_node_id: 0,
-                    dst_node_id: 0,
-                    home_id: 0,
-                    id: 0,
-                    timestamp_delta: 0,
-                    speed: 0,
-                    rssi: 0,
-                    channel: 0,
-                    payload: String::new(),
-                    payload_raw: Vec::new(),
-                });
+                self.toggle_filter();
                 Ok(false)
             }
             KeyCode::Char('s') => {
-                self.start();
+                self.capture(true);
                 Ok(false)
             }
             KeyCode::Char('S') => {
-                self.stop();
+                self.capture(false);
                 Ok(false)
             }
             _ => Ok(false),
@@ -331,8 +609,71 @@ impl App {
 #[command(about = "zniff-rs-tui is a tool for sniffing, parsing and converting Z-Wave data.", long_about = None)]
 struct Cli {
     /// Path to the ZLF file to read frames from.
-    #[arg(short, long)]
-    trace: String,
+    #[arg(short, long, required_unless_present = "serial", conflicts_with = "serial")]
+    trace: Option<String>,
+
+    /// Capture live from a Zniffer on this serial port, e.g., COM3 or /dev/ttyUSB0.
+    #[arg(long, requires = "region")]
+    serial: Option<String>,
+
+    /// Z-Wave region of the live capture, e.g., US, EU, etc.
+    #[arg(long, value_parser = clap::value_parser!(Region), requires = "serial")]
+    region: Option<Region>,
+
+    /// Only list frames addressed to this node, including multicasts. Press
+    /// n to filter on the destination of the selected frame or to clear the
+    /// filter.
+    #[arg(long)]
+    node: Option<u16>,
+
+    /// Command class definitions in the format of zwave.xml, merged on top
+    /// of the built-in ones, e.g., newer or proprietary command classes.
+    #[arg(long, env = "ZNIFF_CC_XML")]
+    cc_xml: Option<PathBuf>,
+
+    /// Frame header definitions in the format of FrameDefinition.xml, merged
+    /// on top of the built-in ones.
+    #[arg(long, env = "ZNIFF_FRAME_DEF")]
+    frame_def: Option<PathBuf>,
+}
+
+/// Forward the frames of a device to the app and start or stop the capture
+/// on request. Stops the capture when the app closes the control channel.
+async fn drive_device<T: Transport>(
+    mut zniffer: Zniffer<T>,
+    events: mpsc::UnboundedSender<DeviceEvent>,
+    mut control: mpsc::UnboundedReceiver<bool>,
+) {
+    loop {
+        let event = tokio::select! {
+            result = zniffer.next_frame() => match result {
+                Ok(frame) => DeviceEvent::Frame(frame),
+                Err(ZnifferError::Stopped) => continue,
+                Err(e) => {
+                    let _ = events.send(DeviceEvent::Error(e.to_string()));
+                    return;
+                },
+            },
+            start = control.recv() => {
+                let Some(start) = start else {
+                    let _ = zniffer.stop().await;
+                    return;
+                };
+                let result = if start {
+                    zniffer.start().await
+                } else {
+                    zniffer.stop().await
+                };
+                match result {
+                    Ok(()) => DeviceEvent::Capturing(start),
+                    Err(e) => DeviceEvent::Error(e.to_string()),
+                }
+            },
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
 }
 
 fn install_panic_hook() {
@@ -349,17 +690,32 @@ async fn main() -> Result<(), io::Error> {
 
     install_panic_hook();
     let cli = Cli::parse();
-
-    let file = File::open(&cli.trace)?;
+    let definitions = match Definitions::load(cli.cc_xml.as_deref(), cli.frame_def.as_deref()) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(());
+        },
+    };
 
     // Create app state
-    let mut app = match App::try_new(file) {
+    let app = match (&cli.trace, &cli.serial, cli.region) {
+        (Some(trace), _, _) => App::try_new(trace, &definitions).map_err(|e| format!("Failed to open '{}': {}", trace, e)),
+        (None, Some(serial), Some(region)) => {
+            App::try_live(serial, region, &definitions).await.map_err(|e| format!("Failed to capture from '{}': {}", serial, e))
+        },
+        _ => Err("Either --trace or --serial and --region is required".to_string()),
+    };
+    let mut app = match app {
         Ok(app) => app,
-        Err(_) => {
-            eprintln!("Failed to create app");
+        Err(e) => {
+            eprintln!("{}", e);
             return Ok(());
         }
     };
+    if cli.node.is_some() {
+        app.filter(cli.node);
+    }
 
     // Setup terminal
     enable_raw_mode()?;
@@ -376,6 +732,8 @@ async fn main() -> Result<(), io::Error> {
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
+    app.shutdown().await;
+
     if let Err(err) = res {
         println!("Error: {:?}", err);
     }
@@ -388,6 +746,7 @@ fn run_app(
     app: &mut App,
 ) -> io::Result<()> {
     loop {
+        app.poll_device();
         terminal.draw(|f| {
             let chunks = Layout::default()
                 .constraints([Constraint::Percentage(100)])
@@ -396,6 +755,7 @@ fn run_app(
             // Calculate visible range for virtual scrolling
             let area_height = chunks[0].height.saturating_sub(2) as usize; // Subtract borders
             let selected = app.state.selected().unwrap_or(0);
+            app.load_until(selected + area_height + 1);
             let total_items = app.items.len();
 
             // Calculate the window of items to display
@@ -405,7 +765,7 @@ fn run_app(
 
             let visible_count = visible_end - visible_start;
 
-            let frames = app.db.get_frames(visible_start, visible_count);
+            let frames = app.frames(visible_start, visible_count);
 
             let mut previous_timestamp: Option<i64> = None;
 
@@ -417,30 +777,44 @@ fn run_app(
                 };
                 previous_timestamp = Some(frame.timestamp);
 
-                let home_id = 0x12345678; // Example home_id, replace with actual value if available
-                let src_node_id = 1; // Example src_node_id, replace with actual value if available
-                let dst_node_id = 2; // Example dst_node_id, replace with actual value
+                let dst_node_id = format_destination(frame);
 
                 // Create string with the raw hex data of the payload.
                 let payload_hex = format!("{:02X?}", frame.payload);
+                let frame_type = FrameType::try_from(frame.frame_type).unwrap_or_default();
+                let payload = match frame_type {
+                    FrameType::Beam | FrameType::BeamStart => format!("Wake-up beam to node {}", dst_node_id),
+                    FrameType::BeamStop => match app.beam_durations.get(&frame.id) {
+                        Some(duration) => format!("Beam stop after {} ms", duration),
+                        None => "Beam stop".to_string(),
+                    },
+                    _ => payload_hex.clone(), // This is supposed to be the parsed payload.
+                };
 
-                Row::new(vec![
-                    Cell::from(frame.id.to_string()),
-                    Cell::from(frame.timestamp.to_string()),
-                    Cell::from(timestamp_delta.to_string()),
+                let row = Row::new(vec![
+                    Cell::from(app.frame_number(frame)),
+                    Cell::from(format_type(frame_type, frame.header_type)),
+                    Cell::from(ZlfTimestamp::from_unix_micros(frame.timestamp).format_time()),
+                    Cell::from(format_delta(timestamp_delta)),
                     Cell::from(frame.speed.to_string()),
                     Cell::from(frame.rssi.to_string()),
                     Cell::from(frame.channel.to_string()),
-                    Cell::from(format!("0x{:08X}", home_id)),
-                    Cell::from(src_node_id.to_string()),
+                    Cell::from(format!("0x{:08X}", frame.home_id)),
+                    Cell::from(frame.src_node_id.to_string()),
                     Cell::from(dst_node_id.to_string()),
-                    Cell::from(payload_hex.clone()), // This is supposed to be the parsed payload.
+                    Cell::from(payload),
                     Cell::from(payload_hex),
-                ])
+                ]);
+                // Highlight frames with checksum errors.
+                match frame.checksum_ok {
+                    true => row,
+                    false => row.style(Style::default().fg(Color::Red)),
+                }
             }).collect::<Vec<Row>>();
 
             let header = [
-                    "ID",
+                    app.frame_number_label(),
+                    "Type",
                     "Timestamp",
                     "ΔTimestamp",
                     "Speed",
@@ -459,7 +833,8 @@ fn run_app(
                 .height(1);
 
             let list = Table::new(items, &[
-                    Constraint::Length(5),
+                    Constraint::Length(8),
+                    Constraint::Length(10),
                     Constraint::Length(15),
                     Constraint::Length(15),
                     Constraint::Length(6),
@@ -474,7 +849,7 @@ fn run_app(
                 .header(header)
                 .block(Block::default()
                     .borders(Borders::ALL)
-                    .title(format!("Frames ({}/{})", selected + 1, total_items)))
+                    .title(format_title(app, selected, total_items)))
                 .row_highlight_style(
                     Style::default()
                         .bg(Color::LightBlue)
@@ -484,30 +859,29 @@ fn run_app(
                 .highlight_symbol(">> ");
 
             // Adjust the state offset for the visible window
-            let mut adjusted_state = app.state.clone();
+            let mut adjusted_state = app.state;
             adjusted_state.select(Some(selected - visible_start));
 
             f.render_stateful_widget(list, chunks[0], &mut adjusted_state);
 
             // Render detail popup if in detail mode
-            if app.mode == AppMode::Detail {
-                if let Some(selected_idx) = app.state.selected() {
-                    if let Some(frame_id) = app.items.get(selected_idx) {
-                        render_detail_popup(f, *frame_id, &app);
-                    }
-                }
+            if app.mode == AppMode::Detail
+                && let Some(selected_idx) = app.state.selected()
+                && let Some(frame_id) = app.items.get(selected_idx)
+            {
+                render_detail_popup(f, *frame_id, app);
             }
         })?;
 
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                let page_size = terminal.size()?.height.saturating_sub(3) as usize;
-                if key.kind == event::KeyEventKind::Press {
-                    // The check for key.kind is needed to avoid handling both press and release on Windows.
-                    let should_exit = app.handle_key_event(key.code, page_size)?;
-                    if should_exit {
-                        return Ok(());
-                    }
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            let page_size = terminal.size()?.height.saturating_sub(3) as usize;
+            if key.kind == event::KeyEventKind::Press {
+                // The check for key.kind is needed to avoid handling both press and release on Windows.
+                let should_exit = app.handle_key_event(key.code, page_size)?;
+                if should_exit {
+                    return Ok(());
                 }
             }
         }
@@ -521,34 +895,45 @@ fn render_detail_popup(f: &mut ratatui::Frame, frame_id: u128, app: &App) {
     // Fetch the frame details from the database using the frame_id
     let frame = match app.db.get_frame(frame_id as u64) {
         Some(frame) => frame,
-        None => DbFrame { id: 0, channel: 0, speed: 0, timestamp: 0, rssi: 0, home_id: 0, src_node_id: 0, dst_node_id: 0, payload: vec![] },
+        None => DbFrame { id: 0, frame_type: 0, region: 0, channel: 0, speed: 0, timestamp: 0, rssi: 0, home_id: 0, header_type: 0, src_node_id: 0, dst_node_id: 0, payload: vec![], checksum_ok: true, multicast: vec![] },
     };
 
     // Format the detailed information
     let detail_text = format!(
         "Frame Details\n\n\
-        ID:               {}\n\
+        {:<18}{}\n\
+        Type:             {}\n\
         Timestamp:        {}\n\
         Timestamp Delta:  {}\n\
         Speed:            {}\n\
         RSSI:             {} dBm\n\
         Channel:          {}\n\
         Home ID:          0x{:08X}\n\
+        MAC Header:       {}\n\
+        Route:            {}\n\
+        Explorer:         {}\n\
+        Checksum:         {}\n\
         Source Node ID:   {}\n\
         Dest Node ID:     {}\n\
         Payload:          {}\n\
         Payload Raw:      {:02X?}\n\n\
         Press Enter or Esc to close",
-        frame.id,
-        frame.timestamp,
+        format!("{}:", app.frame_number_label()),
+        app.frame_number(&frame),
+        format_type(FrameType::try_from(frame.frame_type).unwrap_or_default(), frame.header_type),
+        ZlfTimestamp::from_unix_micros(frame.timestamp),
         0, //frame.timestamp_delta,
         frame.speed,
         frame.rssi,
         frame.channel,
-        0x12345678, //frame.home_id,
-        1, //frame.src_node_id,
-        2, //frame.dst_node_id,
-        "frame.payload",
+        frame.home_id,
+        format_mac_header(&frame),
+        format_route(&frame),
+        format_explorer(&frame),
+        if frame.checksum_ok { "OK" } else { "error" },
+        frame.src_node_id,
+        format_destination(&frame),
+        app.commands.get(&frame.id).map_or("-", String::as_str),
         frame.payload, //frame.payload_raw
     );
 
@@ -567,6 +952,170 @@ fn render_detail_popup(f: &mut ratatui::Frame, frame_id: u128, app: &App) {
     f.render_widget(paragraph, area);
 }
 
+fn format_title(app: &App, selected: usize, total_items: usize) -> String {
+    let title = match &app.source {
+        Source::Trace { start: 0, .. } if app.loaded => format!("Frames ({}/{})", selected + 1, total_items),
+        Source::Trace { index, start: 0, .. } => {
+            format!("Frames ({}/{}+ of {} records)", selected + 1, total_items, index.frame_count())
+        },
+        Source::Trace { index, start, .. } => format!(
+            "Frames ({}/{} from record {} of {}) - Home for the first",
+            selected + 1,
+            total_items,
+            start + 1,
+            index.frame_count(),
+        ),
+        Source::Live { capturing, error, .. } => {
+            let state = match error {
+                Some(e) => format!("error: {}", e),
+                None if *capturing => "capturing, s/S to start/stop".to_string(),
+                None => "stopped, s/S to start/stop".to_string(),
+            };
+            let follow = if app.follow { ", following" } else { "" };
+            format!("Frames ({}/{}) - {}{}", (selected + 1).min(total_items), total_items, state, follow)
+        },
+    };
+    match app.node_filter {
+        Some(node_id) => format!("{} - to node {}, n to clear", title, node_id),
+        None => title,
+    }
+}
+
+/// The MAC header type of data frames, otherwise the frame type.
+fn format_type(frame_type: FrameType, header_type: u8) -> &'static str {
+    match (frame_type, HeaderType::try_from(header_type)) {
+        (FrameType::Data, Ok(HeaderType::Singlecast)) => "Singlecast",
+        (FrameType::Data, Ok(HeaderType::Multicast)) => "Multicast",
+        (FrameType::Data, Ok(HeaderType::TransferAcknowledge)) => "Ack",
+        (FrameType::Data, Ok(HeaderType::Flooded)) => "Flooded",
+        (FrameType::Data, Ok(HeaderType::Explorer)) => "Explorer",
+        (FrameType::Data, Ok(HeaderType::Routed)) => "Routed",
+        (FrameType::Command, _) => "Command",
+        (FrameType::Data, Err(_)) => "Data",
+        (FrameType::Beam, _) => "Beam",
+        (FrameType::BeamStart, _) => "Beam start",
+        (FrameType::BeamStop, _) => "Beam stop",
+    }
+}
+
+/// The flags, sequence number and length of the MAC header of a data frame.
+fn format_mac_header(frame: &DbFrame) -> String {
+    if frame.frame_type != FrameType::Data as u8 {
+        return "-".to_string();
+    }
+    let layout = mac::Layout::for_frame(Region::try_from(frame.region).unwrap_or_default(), frame.speed);
+    match MacHeader::decode(&frame.payload, layout) {
+        Ok(header) => {
+            let flags = [
+                (header.ack, "ack"),
+                (header.routed, "routed"),
+                (header.low_power, "low power"),
+                (header.speed_modified, "speed modified"),
+            ]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+            let mut text = format!(
+                "{:?} [{}], sequence {}, length {}",
+                header.header_type,
+                flags.join(", "),
+                header.sequence_number,
+                header.length,
+            );
+            if let Some(lr) = header.long_range {
+                text += &format!(", noise floor {} dBm, TX power {} dBm", lr.noise_floor, lr.tx_power);
+                if let Some(rssi) = lr.received_rssi {
+                    text += &format!(", received RSSI {} dBm", rssi);
+                }
+            }
+            text
+        },
+        Err(e) => e.to_string(),
+    }
+}
+
+/// The destination node ID, or the node IDs addressed by a multicast.
+fn format_destination(frame: &DbFrame) -> String {
+    match frame.multicast.as_slice() {
+        [] => frame.dst_node_id.to_string(),
+        node_ids => node_ids.iter().map(|node_id| node_id.to_string()).collect::<Vec<_>>().join(","),
+    }
+}
+
+/// The path of a routed frame, e.g., "1 -> 5 -> 7 -> 11, Outbound, hop 1".
+fn format_route(frame: &DbFrame) -> String {
+    let layout = mac::Layout::for_frame(Region::try_from(frame.region).unwrap_or_default(), frame.speed);
+    let Some(route) = MacHeader::decode(&frame.payload, layout).ok().and_then(|header| header.route) else {
+        return "-".to_string();
+    };
+    // Inbound frames travel the repeaters backwards.
+    let mut path = vec![frame.src_node_id.to_string()];
+    match route.direction {
+        Direction::Outbound => path.extend(route.repeaters.iter().map(|r| r.to_string())),
+        Direction::Inbound => path.extend(route.repeaters.iter().rev().map(|r| r.to_string())),
+    }
+    path.push(frame.dst_node_id.to_string());
+
+    let mut text = format!("{}, {:?}, hop {}", path.join(" -> "), route.direction, route.hop);
+    if route.ack {
+        text += ", acknowledgement";
+    }
+    if let Some(failed_hop) = route.failed_hop {
+        text += &format!(", error at hop {}", failed_hop);
+    }
+    text
+}
+
+/// The command, flags and repeaters of an explorer frame.
+fn format_explorer(frame: &DbFrame) -> String {
+    let layout = mac::Layout::for_frame(Region::try_from(frame.region).unwrap_or_default(), frame.speed);
+    let Some(explorer) = MacHeader::decode(&frame.payload, layout).ok().and_then(|header| header.explorer) else {
+        return "-".to_string();
+    };
+    let command = match &explorer.command {
+        ExploreCommand::Normal => "Normal".to_string(),
+        ExploreCommand::Autoinclusion { network_home_id } => format!("Autoinclusion into 0x{:08X}", network_home_id),
+        ExploreCommand::SearchResult { node_id, frame_handle, ttl, repeaters } => format!(
+            "Search result for node {} (frame handle {}, TTL {}, repeaters {:?})",
+            node_id, frame_handle, ttl, repeaters,
+        ),
+    };
+    let flags = [
+        (explorer.source_routed, "source routed"),
+        (explorer.stop, "stop"),
+        (explorer.stop_request, "stop request"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>();
+    format!(
+        "{}, {:?} [{}], TTL {}, repeaters {:?}, session TX random interval {}",
+        command,
+        explorer.direction,
+        flags.join(", "),
+        explorer.ttl,
+        explorer.repeaters,
+        explorer.session_tx_random_interval,
+    )
+}
+
+/// The command class, command and parameters of a data frame, one per line.
+fn format_command(decoded: &DecodedFrame) -> String {
+    match (&decoded.command, decoded.payload.first()) {
+        // Align the parameters with the other values of the popup.
+        (Some(command), _) => command.to_string().trim_end().replace('\n', "\n                  "),
+        (None, Some(key)) => format!("Unknown command class 0x{:02X}", key),
+        (None, None) => "-".to_string(),
+    }
+}
+
+/// Format a timestamp delta in microseconds as milliseconds.
+fn format_delta(micros: i64) -> String {
+    format!("{:.3} ms", micros as f64 / 1000.0)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::vertical([
         Constraint::Percentage((100 - percent_y) / 2),
@@ -582,3 +1131,118 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     ])
     .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use zniff_rs_core::device::Emulator;
+
+    type Writer = zniff_rs_core::zlf::ZlfWriter<std::io::Cursor<Vec<u8>>>;
+
+    /// Write a trace to the temporary directory.
+    fn write_trace(name: &str, write: impl FnOnce(&mut Writer)) -> PathBuf {
+        let mut writer = Writer::new(std::io::Cursor::new(Vec::new())).unwrap();
+        write(&mut writer);
+        let path = std::env::temp_dir().join(format!("zniff-rs-{}-{}.zlf", std::process::id(), name));
+        std::fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+        path
+    }
+
+    /// Record numbers and timestamps of the listed frames.
+    fn rows(app: &App) -> Vec<(String, i64)> {
+        app.frames(0, app.items.len()).iter().map(|frame| (app.frame_number(frame), frame.timestamp)).collect()
+    }
+
+    #[test]
+    fn test_go_to_end() {
+        let frame = [
+            0x21, 0x01, 0x6D, 0xCE, 0x20, 0x00, 0x9D, 0x21, 0x03, 0x0A,
+            0xE2, 0xEA, 0x36, 0xC3, 0x01, 0x81, 0x0D, 0x12, 0x20, 0x0B,
+        ];
+        let path = write_trace("end", |writer| {
+            // Start command responses and records of three frames.
+            for i in 0..END_WINDOW as u64 + 200 {
+                match i % 2 {
+                    0 => writer.write_zniffer(ZlfTimestamp(i), &[0x23, 0x04, 0x00]).unwrap(),
+                    _ => writer.write_zniffer(ZlfTimestamp(i), &frame.repeat(3)).unwrap(),
+                }
+            }
+        });
+        let definitions = Definitions::default();
+
+        // Only the last records are read.
+        let mut app = App::try_new(path.to_str().unwrap(), &definitions).unwrap();
+        app.go_to_end();
+        assert_eq!(app.items.len(), END_WINDOW / 2 * 3);
+        assert_eq!(app.state.selected(), Some(END_WINDOW / 2 * 3 - 1));
+        assert!(format_title(&app, 0, app.items.len()).contains("from record 201 of 1200"));
+        let end = rows(&app);
+        assert_eq!(end[0].0, "202");
+
+        // The same frames have the same record numbers when read from the start.
+        app.go_to_start();
+        app.load_until(usize::MAX);
+        let all = rows(&app);
+        assert_eq!(all.len(), (END_WINDOW + 200) / 2 * 3);
+        assert_eq!(all[all.len() - end.len()..], end);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_versions() {
+        // Node 11 reports version 1 of the Basic Command Class, then sends a
+        // Basic Report, which has a single parameter in version 1. The frames
+        // are received at 100 kbit/s, with a 2-byte checksum.
+        let path = write_trace("versions", |writer| {
+            let mpdus = [
+                vec![0xE2, 0xEA, 0x36, 0xC3, 0x0B, 0x41, 0x01, 0x0F, 0x01, 0x86, 0x14, 0x20, 0x01, 0x00, 0x00],
+                vec![0xE2, 0xEA, 0x36, 0xC3, 0x0B, 0x41, 0x02, 0x0E, 0x01, 0x20, 0x03, 0xFF, 0x00, 0x00],
+            ];
+            for (i, payload) in mpdus.into_iter().enumerate() {
+                let pti_frame = Frame { payload, ..Default::default() }.to_pti_vector().unwrap();
+                writer.write_pti(ZlfTimestamp(i as u64), &pti_frame).unwrap();
+            }
+        });
+        let definitions = Definitions::default();
+        let mut app = App::try_new(path.to_str().unwrap(), &definitions).unwrap();
+        app.go_to_end();
+        assert_eq!(app.decoder.version(11, 0x20), Some(1));
+        assert!(app.commands[&2].contains("(Version 1): Basic Report"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drive_device() {
+        let frames: Vec<Frame> = (0..3u8)
+            .map(|i| Frame {
+                timestamp: i as u16,
+                payload: vec![0xE2, 0xEA, 0x36, 0xC3, i, 0x41, 0x0C, 0x0B, 0x02],
+                ..Default::default()
+            })
+            .collect();
+        let (host, device) = device::in_memory();
+        let emulator = tokio::spawn(Emulator::new(frames.clone()).interval(Duration::from_millis(1)).run(device));
+
+        let (event_tx, mut events) = mpsc::unbounded_channel();
+        let (control, control_rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(drive_device(Zniffer::new(host), event_tx, control_rx));
+
+        control.send(true).unwrap();
+        assert!(matches!(events.recv().await, Some(DeviceEvent::Capturing(true))));
+        for frame in &frames {
+            let Some(DeviceEvent::Frame(received)) = events.recv().await else {
+                panic!("expected a frame");
+            };
+            assert_eq!(&received, frame);
+        }
+        control.send(false).unwrap();
+        assert!(matches!(events.recv().await, Some(DeviceEvent::Capturing(false))));
+
+        // The device goes away.
+        emulator.abort();
+        assert!(matches!(events.recv().await, Some(DeviceEvent::Error(_))));
+        task.await.unwrap();
+        assert!(events.recv().await.is_none());
+    }
+}